@@ -0,0 +1,184 @@
+// compares the boxed `Stmt`/`Expr` AST with the arena one in `compiler::arena`.
+//
+// run with `cargo bench --bench ast`. it reports the time to build, traverse and
+// drop each form of a generated program, and the number of heap allocations
+// each one holds. both are built from the same tokens the way the compiler
+// builds them: the boxed AST is read off the syntax tree, the parser emits the
+// arena directly.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use compiler::arena::{self, Ast, ExprId, ExprNode};
+use compiler::ast;
+use compiler::parser::{Expr, Parser, Stmt};
+use compiler::syntax::{build_tree, SyntaxNode};
+use compiler::tokenizer::{Token, Tokenizer};
+use compiler::visit::{self, Visitor};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static DEALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const ITERATIONS: u32 = 20;
+
+fn source(copies: usize) -> String {
+    let mut source = String::new();
+    for index in 0..copies {
+        source.push_str(&format!(
+            "struct Point{index} {{ x: int, y: int, }}\n\
+             let p{index} = Point{index} {{ x: {index}, y: {index} * 2 }};\n\
+             let total{index} = (p{index}.x + p{index}.y) * 3 - values[{index}] / 2;\n\
+             if (total{index} > 10) {{ print(total{index}, p{index}.x == 1 ? \"a\" : \"b\"); }}\n\
+             match (total{index} % 3) {{ 0 => print(0), n => {{ p{index}.x = n; }} }}\n"
+        ));
+    }
+    source
+}
+
+fn parse_boxed(source: &str, tokens: &Vec<Token>) -> Vec<Stmt> {
+    let mut parser = Parser::new(tokens);
+    parser.parse();
+    let root = SyntaxNode::new_root(std::rc::Rc::new(build_tree(source, tokens, parser.events())));
+    ast::lower(source, tokens, &root)
+}
+
+fn parse_arena(tokens: &Vec<Token>) -> Ast {
+    Ast::parse(tokens).0
+}
+
+// runs `f` `ITERATIONS` times and returns the fastest run
+fn measure<T>(mut f: impl FnMut() -> T) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        black_box(f());
+        best = best.min(start.elapsed());
+    }
+    best
+}
+
+fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let value = f();
+    (value, ALLOCATIONS.load(Ordering::Relaxed) - before)
+}
+
+// the number of allocations `value` owns, counted by what dropping it frees
+fn held<T>(value: T) -> usize {
+    let before = DEALLOCATIONS.load(Ordering::Relaxed);
+    drop(value);
+    DEALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+#[derive(Default)]
+struct BoxedCounter {
+    identifiers: usize,
+    nodes: usize,
+}
+
+impl Visitor for BoxedCounter {
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.nodes += 1;
+        if let Expr::IDENTIFIER(_, _) = expr {
+            self.identifiers += 1;
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+struct ArenaCounter<'a> {
+    ast: &'a Ast,
+    identifiers: usize,
+    nodes: usize,
+}
+
+impl<'a> ArenaCounter<'a> {
+    fn new(ast: &'a Ast) -> ArenaCounter<'a> {
+        ArenaCounter { ast, identifiers: 0, nodes: 0 }
+    }
+}
+
+impl<'a> arena::Visitor<'a> for ArenaCounter<'a> {
+    fn ast(&self) -> &'a Ast {
+        self.ast
+    }
+
+    fn visit_expr(&mut self, expr: ExprId) {
+        self.nodes += 1;
+        if let ExprNode::IDENTIFIER(_, _) = self.ast.expr(expr) {
+            self.identifiers += 1;
+        }
+        arena::walk_expr(self, expr);
+    }
+}
+
+fn main() {
+    let source = source(2000);
+    let tokens = Tokenizer::tokenize(&source);
+
+    let boxed_time = measure(|| parse_boxed(&source, &tokens));
+    let arena_time = measure(|| parse_arena(&tokens));
+
+    let (boxed, boxed_allocations) = allocations(|| parse_boxed(&source, &tokens));
+    let (ast, arena_allocations) = allocations(|| parse_arena(&tokens));
+    assert!(ast == Ast::from_program(&boxed));
+
+    let boxed_walk = measure(|| {
+        let mut counter = BoxedCounter::default();
+        counter.visit_program(&boxed);
+        (counter.nodes, counter.identifiers)
+    });
+    let arena_walk = measure(|| {
+        let mut counter = ArenaCounter::new(&ast);
+        arena::Visitor::visit_program(&mut counter, ast.program());
+        (counter.nodes, counter.identifiers)
+    });
+
+    let mut boxed_counter = BoxedCounter::default();
+    boxed_counter.visit_program(&boxed);
+    let mut arena_counter = ArenaCounter::new(&ast);
+    arena::Visitor::visit_program(&mut arena_counter, ast.program());
+    assert_eq!(boxed_counter.nodes, arena_counter.nodes);
+    assert_eq!(boxed_counter.identifiers, arena_counter.identifiers);
+
+    let boxed_drop = measure_drop(|| parse_boxed(&source, &tokens));
+    let arena_drop = measure_drop(|| parse_arena(&tokens));
+
+    println!("{} bytes of source, {} statements, {} expressions", source.len(), ast.stmt_count(), ast.expr_count());
+    println!("parse     boxed {:>10.2?}   arena {:>10.2?}", boxed_time, arena_time);
+    println!("traverse  boxed {:>10.2?}   arena {:>10.2?}", boxed_walk, arena_walk);
+    println!("drop      boxed {:>10.2?}   arena {:>10.2?}", boxed_drop, arena_drop);
+    println!("allocations made  boxed {:>10}   arena {:>10}", boxed_allocations, arena_allocations);
+    println!("allocations held  boxed {:>10}   arena {:>10}", held(boxed), held(ast));
+}
+
+// drop time only, building the value is not timed
+fn measure_drop<T>(mut build: impl FnMut() -> T) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..ITERATIONS {
+        let value = build();
+        let start = Instant::now();
+        drop(value);
+        best = best.min(start.elapsed());
+    }
+    best
+}
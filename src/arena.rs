@@ -0,0 +1,815 @@
+// index based representation of the AST.
+//
+// every statement and expression lives in a typed vector and refers to its
+// children through ids, lists of children are ranges into shared side vectors
+// and names are interned, so a whole program takes a handful of allocations
+// instead of one per node. ids are dense and stay the same for the lifetime of
+// the `Ast`, which makes them usable as keys for data computed about a node.
+//
+// the parser emits the nodes straight into an `Ast` through `Sink`, see
+// `Ast::parse`. the variants mirror `parser::Stmt`/`parser::Expr` one to one and
+// `Visitor` mirrors `visit::Visitor`, so a pass can be written against either form.
+
+use std::collections::HashMap;
+
+use crate::error::ParseError;
+use crate::parser::{covering, Expr, FieldDeclaration, FieldInitializer, MatchArm, Parameter, Parser, Pattern, Stmt};
+use crate::tokenizer::{Span, Token, TokenKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StmtId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExprId(u32);
+
+// an interned name or string literal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+// a run of consecutive entries in one of the list vectors of `Ast`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListRange {
+    start: u32,
+    end: u32,
+}
+
+impl ListRange {
+    pub fn len(&self) -> usize {
+        (self.end - self.start) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    fn indices(&self) -> std::ops::Range<usize> {
+        self.start as usize..self.end as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Neq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+impl BinaryOp {
+    pub fn from_token(kind: &TokenKind) -> Option<BinaryOp> {
+        match kind {
+            TokenKind::ADD => Some(BinaryOp::Add),
+            TokenKind::SUB => Some(BinaryOp::Sub),
+            TokenKind::MUL => Some(BinaryOp::Mul),
+            TokenKind::DIV => Some(BinaryOp::Div),
+            TokenKind::MOD => Some(BinaryOp::Mod),
+            TokenKind::EQ => Some(BinaryOp::Eq),
+            TokenKind::NEQ => Some(BinaryOp::Neq),
+            TokenKind::LT => Some(BinaryOp::Lt),
+            TokenKind::LTEQ => Some(BinaryOp::LtEq),
+            TokenKind::GT => Some(BinaryOp::Gt),
+            TokenKind::GTEQ => Some(BinaryOp::GtEq),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StmtNode {
+    EOF,
+    VariableDeclaration(VariableDeclarationNode),
+    // a range of `Ast::field_declarations`
+    StructDeclaration(Symbol, ListRange),
+    FunctionDeclaration(FunctionDeclarationNode),
+    IFSTATEMENT(ExprId, StmtId),
+    WHILE(ExprId, StmtId),
+    RETURN(Option<ExprId>, Span),
+    BREAK(Span),
+    CONTINUE(Span),
+    // a range of `Ast::stmt_list`
+    Block(ListRange),
+    // a range of `Ast::match_arms`
+    MATCH(ExprId, ListRange),
+    IMPORT(Symbol, Span),
+    EXPR(ExprId, Span),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExprNode {
+    INTEGERLITERAL(i32, Span),
+    FLOATLITERAL(f32, Span),
+    STRINGLITERAL(Symbol, Span),
+    BOOLLITERAL(bool, Span),
+    IDENTIFIER(Symbol, Span),
+    // the span is the operator's
+    BINARYEXPR(ExprId, ExprId, BinaryOp, Span),
+    PARENTHESIZED(ExprId),
+    UNARY(ExprId),
+    // a range of `Ast::expr_list`
+    ARRAY(ListRange),
+    INDEX(ExprId, ExprId),
+    ASSIGNMENT(ExprId, ExprId),
+    MEMBER(ExprId, Symbol),
+    // arguments are a range of `Ast::expr_list`
+    CALL(ExprId, ListRange),
+    // a range of `Ast::symbol_list`
+    PATH(ListRange),
+    // a range of `Ast::field_initializers`
+    STRUCTLITERAL(Symbol, ListRange),
+    CONDITIONAL(ExprId, ExprId, ExprId),
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypeAnnotationNode {
+    pub name: Symbol,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VariableDeclarationNode {
+    pub name: Symbol,
+    pub span: Span,
+    pub constant: bool,
+    pub annotation: Option<TypeAnnotationNode>,
+    pub initializer: Option<ExprId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParameterNode {
+    pub name: Symbol,
+    pub span: Span,
+    pub annotation: TypeAnnotationNode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FunctionDeclarationNode {
+    pub name: Symbol,
+    pub span: Span,
+    pub signature: Span,
+    // a range of `Ast::parameters`
+    pub parameters: ListRange,
+    pub return_type: Option<TypeAnnotationNode>,
+    pub body: StmtId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldDeclarationNode {
+    pub name: Symbol,
+    pub type_name: Symbol,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldInitializerNode {
+    pub name: Symbol,
+    pub value: ExprId,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternNode {
+    Literal(ExprId, Span),
+    Wildcard(Span),
+    Binding(Symbol, Span),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatchArmNode {
+    pub pattern: PatternNode,
+    pub body: StmtId,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Ast {
+    stmts: Vec<StmtNode>,
+    exprs: Vec<ExprNode>,
+    stmt_lists: Vec<StmtId>,
+    expr_lists: Vec<ExprId>,
+    symbol_lists: Vec<Symbol>,
+    field_declarations: Vec<FieldDeclarationNode>,
+    parameters: Vec<ParameterNode>,
+    field_initializers: Vec<FieldInitializerNode>,
+    match_arms: Vec<MatchArmNode>,
+    names: Vec<String>,
+    symbols: HashMap<String, Symbol>,
+    program: Vec<StmtId>,
+}
+
+// what the parser emits nodes into. `Ast` keeps them, `()` drops them and hands
+// out placeholder ids, the parser then only records the events of the syntax tree
+pub trait Sink {
+    fn intern(&mut self, name: &str) -> Symbol;
+    fn stmt(&mut self, node: StmtNode) -> StmtId;
+    fn expr(&mut self, node: ExprNode) -> ExprId;
+    fn stmt_list(&mut self, entries: &[StmtId]) -> ListRange;
+    fn expr_list(&mut self, entries: &[ExprId]) -> ListRange;
+    fn symbol_list(&mut self, entries: &[Symbol]) -> ListRange;
+    fn field_declarations(&mut self, entries: &[FieldDeclarationNode]) -> ListRange;
+    fn parameters(&mut self, entries: &[ParameterNode]) -> ListRange;
+    fn field_initializers(&mut self, entries: &[FieldInitializerNode]) -> ListRange;
+    fn match_arms(&mut self, entries: &[MatchArmNode]) -> ListRange;
+    // a statement at the top of the file
+    fn top_level(&mut self, stmt: StmtId);
+}
+
+impl Sink for Ast {
+    fn intern(&mut self, name: &str) -> Symbol {
+        Ast::intern(self, name)
+    }
+
+    fn stmt(&mut self, node: StmtNode) -> StmtId {
+        self.push_stmt(node)
+    }
+
+    fn expr(&mut self, node: ExprNode) -> ExprId {
+        self.push_expr(node)
+    }
+
+    fn stmt_list(&mut self, entries: &[StmtId]) -> ListRange {
+        Ast::list(&mut self.stmt_lists, entries)
+    }
+
+    fn expr_list(&mut self, entries: &[ExprId]) -> ListRange {
+        Ast::list(&mut self.expr_lists, entries)
+    }
+
+    fn symbol_list(&mut self, entries: &[Symbol]) -> ListRange {
+        Ast::list(&mut self.symbol_lists, entries)
+    }
+
+    fn field_declarations(&mut self, entries: &[FieldDeclarationNode]) -> ListRange {
+        Ast::list(&mut self.field_declarations, entries)
+    }
+
+    fn parameters(&mut self, entries: &[ParameterNode]) -> ListRange {
+        Ast::list(&mut self.parameters, entries)
+    }
+
+    fn field_initializers(&mut self, entries: &[FieldInitializerNode]) -> ListRange {
+        Ast::list(&mut self.field_initializers, entries)
+    }
+
+    fn match_arms(&mut self, entries: &[MatchArmNode]) -> ListRange {
+        Ast::list(&mut self.match_arms, entries)
+    }
+
+    fn top_level(&mut self, stmt: StmtId) {
+        self.program.push(stmt);
+    }
+}
+
+impl Sink for () {
+    fn intern(&mut self, _name: &str) -> Symbol {
+        Symbol(0)
+    }
+
+    fn stmt(&mut self, _node: StmtNode) -> StmtId {
+        StmtId(0)
+    }
+
+    fn expr(&mut self, _node: ExprNode) -> ExprId {
+        ExprId(0)
+    }
+
+    fn stmt_list(&mut self, _entries: &[StmtId]) -> ListRange {
+        ListRange { start: 0, end: 0 }
+    }
+
+    fn expr_list(&mut self, _entries: &[ExprId]) -> ListRange {
+        ListRange { start: 0, end: 0 }
+    }
+
+    fn symbol_list(&mut self, _entries: &[Symbol]) -> ListRange {
+        ListRange { start: 0, end: 0 }
+    }
+
+    fn field_declarations(&mut self, _entries: &[FieldDeclarationNode]) -> ListRange {
+        ListRange { start: 0, end: 0 }
+    }
+
+    fn parameters(&mut self, _entries: &[ParameterNode]) -> ListRange {
+        ListRange { start: 0, end: 0 }
+    }
+
+    fn field_initializers(&mut self, _entries: &[FieldInitializerNode]) -> ListRange {
+        ListRange { start: 0, end: 0 }
+    }
+
+    fn match_arms(&mut self, _entries: &[MatchArmNode]) -> ListRange {
+        ListRange { start: 0, end: 0 }
+    }
+
+    fn top_level(&mut self, _stmt: StmtId) {}
+}
+
+impl Ast {
+    // parses the tokens straight into the arena, without building the boxed AST
+    // or the syntax tree
+    pub fn parse(tokens: &Vec<Token>) -> (Ast, Vec<ParseError>) {
+        let mut parser = Parser::with_sink(tokens, Ast::default());
+        parser.parse();
+        (parser.sink, parser.errors)
+    }
+
+    // the same arena `Ast::parse` builds for the tokens `program` was read from
+    pub fn from_program(program: &[Stmt]) -> Ast {
+        let mut ast = Ast::default();
+        ast.program = program.iter().map(|stmt| ast.lower_stmt(stmt)).collect();
+        ast
+    }
+
+    pub fn program(&self) -> &[StmtId] {
+        &self.program
+    }
+
+    pub fn stmt(&self, id: StmtId) -> &StmtNode {
+        &self.stmts[id.0 as usize]
+    }
+
+    pub fn stmt_mut(&mut self, id: StmtId) -> &mut StmtNode {
+        &mut self.stmts[id.0 as usize]
+    }
+
+    pub fn expr(&self, id: ExprId) -> &ExprNode {
+        &self.exprs[id.0 as usize]
+    }
+
+    pub fn expr_mut(&mut self, id: ExprId) -> &mut ExprNode {
+        &mut self.exprs[id.0 as usize]
+    }
+
+    // the source an expression covers, the same as `Expr::span` of the node it was lowered from
+    pub fn span(&self, id: ExprId) -> Option<Span> {
+        let first = |range: ListRange| self.expr_list(range).first().and_then(|first| self.span(*first));
+        let last = |range: ListRange| self.expr_list(range).last().and_then(|last| self.span(*last));
+        match *self.expr(id) {
+            ExprNode::INTEGERLITERAL(_, span)
+            | ExprNode::FLOATLITERAL(_, span)
+            | ExprNode::STRINGLITERAL(_, span)
+            | ExprNode::BOOLLITERAL(_, span)
+            | ExprNode::IDENTIFIER(_, span) => Some(span),
+            ExprNode::BINARYEXPR(left, right, _, _) | ExprNode::INDEX(left, right) | ExprNode::ASSIGNMENT(left, right) => {
+                covering(self.span(left), self.span(right))
+            }
+            ExprNode::CONDITIONAL(condition, _, otherwise) => covering(self.span(condition), self.span(otherwise)),
+            ExprNode::PARENTHESIZED(inner) | ExprNode::UNARY(inner) | ExprNode::MEMBER(inner, _) => self.span(inner),
+            ExprNode::ARRAY(elements) => covering(first(elements), last(elements)),
+            ExprNode::CALL(callee, arguments) => covering(self.span(callee), last(arguments)),
+            ExprNode::STRUCTLITERAL(_, fields) => {
+                let fields = self.field_initializers(fields);
+                covering(
+                    fields.first().map(|first| first.span),
+                    fields.last().and_then(|last| self.span(last.value).or(Some(last.span))),
+                )
+            }
+            ExprNode::PATH(_) | ExprNode::Error => None,
+        }
+    }
+
+    // the source a statement covers as far as its parts record it, keywords that
+    // aren't kept in the tree like `let` and `if` are left out
+    pub fn stmt_span(&self, id: StmtId) -> Option<Span> {
+        let statements = |range: ListRange| {
+            let statements = self.stmt_list(range);
+            let first = statements.first().and_then(|first| self.stmt_span(*first));
+            covering(first, statements.last().and_then(|last| self.stmt_span(*last)))
+        };
+        match *self.stmt(id) {
+            StmtNode::EOF => None,
+            StmtNode::VariableDeclaration(declaration) => {
+                covering(Some(declaration.span), declaration.initializer.and_then(|initializer| self.span(initializer)))
+            }
+            StmtNode::StructDeclaration(_, fields) => {
+                let fields = self.field_declarations(fields);
+                covering(fields.first().map(|first| first.span), fields.last().map(|last| last.span))
+            }
+            StmtNode::FunctionDeclaration(function) => covering(Some(function.signature), self.stmt_span(function.body)),
+            StmtNode::IFSTATEMENT(condition, body) | StmtNode::WHILE(condition, body) => {
+                covering(self.span(condition), self.stmt_span(body))
+            }
+            StmtNode::Block(list) => statements(list),
+            StmtNode::MATCH(scrutinee, arms) => {
+                covering(self.span(scrutinee), self.match_arms(arms).last().and_then(|last| self.stmt_span(last.body)))
+            }
+            StmtNode::RETURN(value, span) => covering(Some(span), value.and_then(|value| self.span(value))),
+            StmtNode::IMPORT(_, span) | StmtNode::EXPR(_, span) | StmtNode::BREAK(span) | StmtNode::CONTINUE(span) => {
+                Some(span)
+            }
+        }
+    }
+
+    pub fn stmt_count(&self) -> usize {
+        self.stmts.len()
+    }
+
+    pub fn expr_count(&self) -> usize {
+        self.exprs.len()
+    }
+
+    pub fn name(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.0 as usize]
+    }
+
+    // the symbol of an already interned name
+    pub fn symbol(&self, name: &str) -> Option<Symbol> {
+        self.symbols.get(name).copied()
+    }
+
+    pub fn stmt_list(&self, range: ListRange) -> &[StmtId] {
+        &self.stmt_lists[range.indices()]
+    }
+
+    pub fn expr_list(&self, range: ListRange) -> &[ExprId] {
+        &self.expr_lists[range.indices()]
+    }
+
+    pub fn symbol_list(&self, range: ListRange) -> &[Symbol] {
+        &self.symbol_lists[range.indices()]
+    }
+
+    pub fn field_declarations(&self, range: ListRange) -> &[FieldDeclarationNode] {
+        &self.field_declarations[range.indices()]
+    }
+
+    pub fn parameters(&self, range: ListRange) -> &[ParameterNode] {
+        &self.parameters[range.indices()]
+    }
+
+    pub fn field_initializers(&self, range: ListRange) -> &[FieldInitializerNode] {
+        &self.field_initializers[range.indices()]
+    }
+
+    pub fn match_arms(&self, range: ListRange) -> &[MatchArmNode] {
+        &self.match_arms[range.indices()]
+    }
+
+    fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(name) {
+            return *symbol;
+        }
+        let symbol = Symbol(self.names.len() as u32);
+        self.names.push(name.to_string());
+        self.symbols.insert(name.to_string(), symbol);
+        symbol
+    }
+
+    fn push_stmt(&mut self, node: StmtNode) -> StmtId {
+        self.stmts.push(node);
+        StmtId(self.stmts.len() as u32 - 1)
+    }
+
+    fn push_expr(&mut self, node: ExprNode) -> ExprId {
+        self.exprs.push(node);
+        ExprId(self.exprs.len() as u32 - 1)
+    }
+
+    // children are lowered before their list is appended, so nested lists
+    // never end up interleaved with the entries of their parent's list
+    fn list<T: Copy>(list: &mut Vec<T>, entries: &[T]) -> ListRange {
+        let start = list.len() as u32;
+        list.extend_from_slice(entries);
+        ListRange { start, end: list.len() as u32 }
+    }
+
+    fn lower_stmt(&mut self, stmt: &Stmt) -> StmtId {
+        let node = match stmt {
+            Stmt::EOF => StmtNode::EOF,
+            Stmt::VariableDeclaration(declaration) => {
+                let name = self.intern(&declaration.name);
+                let annotation = declaration
+                    .annotation
+                    .as_ref()
+                    .map(|annotation| TypeAnnotationNode { name: self.intern(&annotation.name), span: annotation.span });
+                let initializer = declaration.initializer.as_ref().map(|initializer| self.lower_expr(initializer));
+                StmtNode::VariableDeclaration(VariableDeclarationNode {
+                    name,
+                    span: declaration.span,
+                    constant: declaration.constant,
+                    annotation,
+                    initializer,
+                })
+            }
+            Stmt::StructDeclaration(name, fields) => {
+                let name = self.intern(name);
+                let fields: Vec<FieldDeclarationNode> =
+                    fields.iter().map(|field| self.lower_field_declaration(field)).collect();
+                StmtNode::StructDeclaration(name, Ast::list(&mut self.field_declarations, &fields))
+            }
+            Stmt::FunctionDeclaration(function) => {
+                let name = self.intern(&function.name);
+                let parameters: Vec<ParameterNode> =
+                    function.parameters.iter().map(|parameter| self.lower_parameter(parameter)).collect();
+                let parameters = Ast::list(&mut self.parameters, &parameters);
+                let return_type = function
+                    .return_type
+                    .as_ref()
+                    .map(|return_type| TypeAnnotationNode { name: self.intern(&return_type.name), span: return_type.span });
+                StmtNode::FunctionDeclaration(FunctionDeclarationNode {
+                    name,
+                    span: function.span,
+                    signature: function.signature,
+                    parameters,
+                    return_type,
+                    body: self.lower_stmt(&function.body),
+                })
+            }
+            Stmt::IFSTATEMENT(condition, body) => {
+                let condition = self.lower_expr(condition);
+                StmtNode::IFSTATEMENT(condition, self.lower_stmt(body))
+            }
+            Stmt::WHILE(condition, body) => {
+                let condition = self.lower_expr(condition);
+                StmtNode::WHILE(condition, self.lower_stmt(body))
+            }
+            Stmt::RETURN(value, span) => {
+                let value = value.as_ref().map(|value| self.lower_expr(value));
+                StmtNode::RETURN(value, *span)
+            }
+            Stmt::BREAK(span) => StmtNode::BREAK(*span),
+            Stmt::CONTINUE(span) => StmtNode::CONTINUE(*span),
+            Stmt::Block(statements) => {
+                let statements: Vec<StmtId> = statements.iter().map(|stmt| self.lower_stmt(stmt)).collect();
+                StmtNode::Block(Ast::list(&mut self.stmt_lists, &statements))
+            }
+            Stmt::MATCH(scrutinee, arms) => {
+                let scrutinee = self.lower_expr(scrutinee);
+                let arms: Vec<MatchArmNode> = arms.iter().map(|arm| self.lower_match_arm(arm)).collect();
+                StmtNode::MATCH(scrutinee, Ast::list(&mut self.match_arms, &arms))
+            }
+            Stmt::IMPORT(path, span) => StmtNode::IMPORT(self.intern(path), *span),
+            Stmt::EXPR(expr, span) => StmtNode::EXPR(self.lower_expr(expr), *span),
+        };
+        self.push_stmt(node)
+    }
+
+    fn lower_field_declaration(&mut self, field: &FieldDeclaration) -> FieldDeclarationNode {
+        FieldDeclarationNode {
+            name: self.intern(&field.name),
+            type_name: self.intern(&field.type_name),
+            span: field.span,
+        }
+    }
+
+    fn lower_parameter(&mut self, parameter: &Parameter) -> ParameterNode {
+        let annotation = &parameter.annotation;
+        ParameterNode {
+            name: self.intern(&parameter.name),
+            span: parameter.span,
+            annotation: TypeAnnotationNode { name: self.intern(&annotation.name), span: annotation.span },
+        }
+    }
+
+    fn lower_field_initializer(&mut self, field: &FieldInitializer) -> FieldInitializerNode {
+        FieldInitializerNode {
+            name: self.intern(&field.name),
+            value: self.lower_expr(&field.value),
+            span: field.span,
+        }
+    }
+
+    fn lower_match_arm(&mut self, arm: &MatchArm) -> MatchArmNode {
+        let pattern = match &arm.pattern {
+            Pattern::Literal(literal, span) => PatternNode::Literal(self.lower_expr(literal), *span),
+            Pattern::Wildcard(span) => PatternNode::Wildcard(*span),
+            Pattern::Binding(name, span) => PatternNode::Binding(self.intern(name), *span),
+        };
+        MatchArmNode { pattern, body: self.lower_stmt(&arm.body) }
+    }
+
+    fn lower_exprs(&mut self, exprs: &[Expr]) -> ListRange {
+        let exprs: Vec<ExprId> = exprs.iter().map(|expr| self.lower_expr(expr)).collect();
+        Ast::list(&mut self.expr_lists, &exprs)
+    }
+
+    fn lower_expr(&mut self, expr: &Expr) -> ExprId {
+        let node = match expr {
+            Expr::INTEGERLITERAL(value, span) => ExprNode::INTEGERLITERAL(*value, *span),
+            Expr::FLOATLITERAL(value, span) => ExprNode::FLOATLITERAL(*value, *span),
+            Expr::STRINGLITERAL(value, span) => ExprNode::STRINGLITERAL(self.intern(value), *span),
+            Expr::BOOLLITERAL(value, span) => ExprNode::BOOLLITERAL(*value, *span),
+            Expr::IDENTIFIER(name, span) => ExprNode::IDENTIFIER(self.intern(name), *span),
+            Expr::BINARYEXPR(left, right, operator, span) => {
+                let left = self.lower_expr(left);
+                let right = self.lower_expr(right);
+                // the parser only builds binary expressions around operator tokens
+                let op = BinaryOp::from_token(operator).expect("binary expression without an operator");
+                ExprNode::BINARYEXPR(left, right, op, *span)
+            }
+            Expr::PARENTHESIZED(inner) => ExprNode::PARENTHESIZED(self.lower_expr(inner)),
+            Expr::UNARY(operand) => ExprNode::UNARY(self.lower_expr(operand)),
+            Expr::ARRAY(elements) => ExprNode::ARRAY(self.lower_exprs(elements)),
+            Expr::INDEX(base, index) => {
+                let base = self.lower_expr(base);
+                ExprNode::INDEX(base, self.lower_expr(index))
+            }
+            Expr::ASSIGNMENT(target, value) => {
+                let target = self.lower_expr(target);
+                ExprNode::ASSIGNMENT(target, self.lower_expr(value))
+            }
+            Expr::MEMBER(base, member) => {
+                let base = self.lower_expr(base);
+                ExprNode::MEMBER(base, self.intern(member))
+            }
+            Expr::CALL(callee, arguments) => {
+                let callee = self.lower_expr(callee);
+                ExprNode::CALL(callee, self.lower_exprs(arguments))
+            }
+            Expr::PATH(segments) => {
+                let segments: Vec<Symbol> = segments.iter().map(|segment| self.intern(segment)).collect();
+                ExprNode::PATH(Ast::list(&mut self.symbol_lists, &segments))
+            }
+            Expr::STRUCTLITERAL(name, fields) => {
+                let name = self.intern(name);
+                let fields: Vec<FieldInitializerNode> =
+                    fields.iter().map(|field| self.lower_field_initializer(field)).collect();
+                ExprNode::STRUCTLITERAL(name, Ast::list(&mut self.field_initializers, &fields))
+            }
+            Expr::CONDITIONAL(condition, then, otherwise) => {
+                let condition = self.lower_expr(condition);
+                let then = self.lower_expr(then);
+                ExprNode::CONDITIONAL(condition, then, self.lower_expr(otherwise))
+            }
+            Expr::Error => ExprNode::Error,
+        };
+        self.push_expr(node)
+    }
+}
+
+// `visit::Visitor` for the arena, nodes are handed out by id and looked up in
+// the `Ast` the visitor walks
+pub trait Visitor<'a> {
+    fn ast(&self) -> &'a Ast;
+
+    fn visit_program(&mut self, program: &[StmtId]) {
+        walk_program(self, program);
+    }
+
+    fn visit_stmt(&mut self, stmt: StmtId) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: ExprId) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_field_declaration(&mut self, field: &FieldDeclarationNode) {
+        walk_field_declaration(self, field);
+    }
+
+    fn visit_field_initializer(&mut self, field: &FieldInitializerNode) {
+        walk_field_initializer(self, field);
+    }
+
+    fn visit_match_arm(&mut self, arm: &MatchArmNode) {
+        walk_match_arm(self, arm);
+    }
+
+    fn visit_pattern(&mut self, pattern: &PatternNode) {
+        walk_pattern(self, pattern);
+    }
+
+    fn visit_span(&mut self, _span: &Span) {}
+}
+
+pub fn walk_program<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, program: &[StmtId]) {
+    for stmt in program {
+        visitor.visit_stmt(*stmt);
+    }
+}
+
+pub fn walk_stmt<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, stmt: StmtId) {
+    let ast = visitor.ast();
+    match *ast.stmt(stmt) {
+        StmtNode::EOF => {}
+        StmtNode::VariableDeclaration(declaration) => {
+            visitor.visit_span(&declaration.span);
+            if let Some(annotation) = declaration.annotation {
+                visitor.visit_span(&annotation.span);
+            }
+            if let Some(initializer) = declaration.initializer {
+                visitor.visit_expr(initializer);
+            }
+        }
+        StmtNode::StructDeclaration(_, fields) => {
+            for field in ast.field_declarations(fields) {
+                visitor.visit_field_declaration(field);
+            }
+        }
+        StmtNode::FunctionDeclaration(function) => {
+            visitor.visit_span(&function.span);
+            visitor.visit_span(&function.signature);
+            for parameter in ast.parameters(function.parameters) {
+                visitor.visit_span(&parameter.span);
+                visitor.visit_span(&parameter.annotation.span);
+            }
+            if let Some(return_type) = function.return_type {
+                visitor.visit_span(&return_type.span);
+            }
+            visitor.visit_stmt(function.body);
+        }
+        StmtNode::IFSTATEMENT(condition, body) | StmtNode::WHILE(condition, body) => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(body);
+        }
+        StmtNode::RETURN(value, span) => {
+            visitor.visit_span(&span);
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        StmtNode::BREAK(span) | StmtNode::CONTINUE(span) => visitor.visit_span(&span),
+        StmtNode::Block(statements) => {
+            for statement in ast.stmt_list(statements) {
+                visitor.visit_stmt(*statement);
+            }
+        }
+        StmtNode::MATCH(scrutinee, arms) => {
+            visitor.visit_expr(scrutinee);
+            for arm in ast.match_arms(arms) {
+                visitor.visit_match_arm(arm);
+            }
+        }
+        StmtNode::IMPORT(_, span) => visitor.visit_span(&span),
+        StmtNode::EXPR(expr, span) => {
+            visitor.visit_expr(expr);
+            visitor.visit_span(&span);
+        }
+    }
+}
+
+pub fn walk_expr<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, expr: ExprId) {
+    let ast = visitor.ast();
+    match *ast.expr(expr) {
+        ExprNode::PATH(_) | ExprNode::Error => {}
+        ExprNode::INTEGERLITERAL(_, span)
+        | ExprNode::FLOATLITERAL(_, span)
+        | ExprNode::STRINGLITERAL(_, span)
+        | ExprNode::BOOLLITERAL(_, span)
+        | ExprNode::IDENTIFIER(_, span) => visitor.visit_span(&span),
+        ExprNode::BINARYEXPR(left, right, _, span) => {
+            visitor.visit_expr(left);
+            visitor.visit_span(&span);
+            visitor.visit_expr(right);
+        }
+        ExprNode::PARENTHESIZED(inner) | ExprNode::UNARY(inner) | ExprNode::MEMBER(inner, _) => {
+            visitor.visit_expr(inner)
+        }
+        ExprNode::ARRAY(elements) => {
+            for element in ast.expr_list(elements) {
+                visitor.visit_expr(*element);
+            }
+        }
+        ExprNode::INDEX(left, right) | ExprNode::ASSIGNMENT(left, right) => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        ExprNode::CALL(callee, arguments) => {
+            visitor.visit_expr(callee);
+            for argument in ast.expr_list(arguments) {
+                visitor.visit_expr(*argument);
+            }
+        }
+        ExprNode::STRUCTLITERAL(_, fields) => {
+            for field in ast.field_initializers(fields) {
+                visitor.visit_field_initializer(field);
+            }
+        }
+        ExprNode::CONDITIONAL(condition, then, otherwise) => {
+            visitor.visit_expr(condition);
+            visitor.visit_expr(then);
+            visitor.visit_expr(otherwise);
+        }
+    }
+}
+
+pub fn walk_field_declaration<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, field: &FieldDeclarationNode) {
+    visitor.visit_span(&field.span);
+}
+
+pub fn walk_field_initializer<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, field: &FieldInitializerNode) {
+    visitor.visit_span(&field.span);
+    visitor.visit_expr(field.value);
+}
+
+pub fn walk_match_arm<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, arm: &MatchArmNode) {
+    visitor.visit_pattern(&arm.pattern);
+    visitor.visit_stmt(arm.body);
+}
+
+pub fn walk_pattern<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, pattern: &PatternNode) {
+    match pattern {
+        PatternNode::Literal(literal, span) => {
+            visitor.visit_expr(*literal);
+            visitor.visit_span(span);
+        }
+        PatternNode::Wildcard(span) | PatternNode::Binding(_, span) => visitor.visit_span(span),
+    }
+}
@@ -0,0 +1,829 @@
+// typed views of the syntax tree.
+//
+// every node kind that stands for a piece of the language gets a wrapper around
+// its `SyntaxNode` with accessors for its parts, `IfStmt::condition` is the
+// expression between the parentheses and `LetStmt::name` the identifier after
+// `let`. a part that broken code left out comes back as `None`.
+//
+// `lower` reads the `Stmt`/`Expr` program of a file off these views, that is how
+// `SyntaxTree::program` is built. the tree only keeps text, so lowering looks the
+// values and positions of tokens up in the token list the tree was built from.
+
+use std::ops::Range;
+
+use crate::parser::{self, FieldDeclaration, FieldInitializer, FunctionDeclaration, Parameter, Pattern, TypeAnnotation, VariableDeclaration};
+use crate::syntax::{token_ranges, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+use crate::tokenizer::{Span, Token, TokenKind};
+
+pub trait AstNode: Sized {
+    fn cast(node: SyntaxNode) -> Option<Self>;
+
+    fn syntax(&self) -> &SyntaxNode;
+}
+
+macro_rules! ast_node {
+    ($name:ident, $kind:ident) => {
+        #[derive(Clone)]
+        pub struct $name(SyntaxNode);
+
+        impl AstNode for $name {
+            fn cast(node: SyntaxNode) -> Option<$name> {
+                match node.kind() {
+                    SyntaxKind::$kind => Some($name(node)),
+                    _ => None,
+                }
+            }
+
+            fn syntax(&self) -> &SyntaxNode {
+                &self.0
+            }
+        }
+    };
+}
+
+ast_node!(Program, PROGRAM);
+ast_node!(LetStmt, LET_STMT);
+ast_node!(IfStmt, IF_STMT);
+ast_node!(WhileStmt, WHILE_STMT);
+ast_node!(ReturnStmt, RETURN_STMT);
+ast_node!(BreakStmt, BREAK_STMT);
+ast_node!(ContinueStmt, CONTINUE_STMT);
+ast_node!(Block, BLOCK);
+ast_node!(StructDecl, STRUCT_DECL);
+ast_node!(FieldDecl, FIELD_DECL);
+ast_node!(FunctionDecl, FUNCTION_DECL);
+ast_node!(ParamList, PARAM_LIST);
+ast_node!(Param, PARAM);
+ast_node!(TypeRef, TYPE);
+ast_node!(MatchStmt, MATCH_STMT);
+ast_node!(MatchArm, MATCH_ARM);
+ast_node!(PatternNode, PATTERN);
+ast_node!(ImportStmt, IMPORT_STMT);
+ast_node!(ExprStmt, EXPR_STMT);
+ast_node!(Literal, LITERAL);
+ast_node!(NameRef, NAME_REF);
+ast_node!(PathExpr, PATH_EXPR);
+ast_node!(BinaryExpr, BINARY_EXPR);
+ast_node!(ParenExpr, PAREN_EXPR);
+ast_node!(UnaryExpr, UNARY_EXPR);
+ast_node!(ArrayExpr, ARRAY_EXPR);
+ast_node!(IndexExpr, INDEX_EXPR);
+ast_node!(AssignExpr, ASSIGN_EXPR);
+ast_node!(MemberExpr, MEMBER_EXPR);
+ast_node!(CallExpr, CALL_EXPR);
+ast_node!(ArgList, ARG_LIST);
+ast_node!(StructLiteral, STRUCT_LITERAL);
+ast_node!(FieldInit, FIELD_INIT);
+ast_node!(ConditionalExpr, CONDITIONAL_EXPR);
+ast_node!(ErrorNode, ERROR);
+
+#[derive(Clone)]
+pub enum Stmt {
+    Let(LetStmt),
+    If(IfStmt),
+    While(WhileStmt),
+    Return(ReturnStmt),
+    Break(BreakStmt),
+    Continue(ContinueStmt),
+    Block(Block),
+    Struct(StructDecl),
+    Function(FunctionDecl),
+    Match(MatchStmt),
+    Import(ImportStmt),
+    Expr(ExprStmt),
+}
+
+impl AstNode for Stmt {
+    fn cast(node: SyntaxNode) -> Option<Stmt> {
+        let stmt = match node.kind() {
+            SyntaxKind::LET_STMT => Stmt::Let(LetStmt(node)),
+            SyntaxKind::IF_STMT => Stmt::If(IfStmt(node)),
+            SyntaxKind::WHILE_STMT => Stmt::While(WhileStmt(node)),
+            SyntaxKind::RETURN_STMT => Stmt::Return(ReturnStmt(node)),
+            SyntaxKind::BREAK_STMT => Stmt::Break(BreakStmt(node)),
+            SyntaxKind::CONTINUE_STMT => Stmt::Continue(ContinueStmt(node)),
+            SyntaxKind::BLOCK => Stmt::Block(Block(node)),
+            SyntaxKind::STRUCT_DECL => Stmt::Struct(StructDecl(node)),
+            SyntaxKind::FUNCTION_DECL => Stmt::Function(FunctionDecl(node)),
+            SyntaxKind::MATCH_STMT => Stmt::Match(MatchStmt(node)),
+            SyntaxKind::IMPORT_STMT => Stmt::Import(ImportStmt(node)),
+            SyntaxKind::EXPR_STMT => Stmt::Expr(ExprStmt(node)),
+            _ => return None,
+        };
+        Some(stmt)
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Stmt::Let(stmt) => stmt.syntax(),
+            Stmt::If(stmt) => stmt.syntax(),
+            Stmt::While(stmt) => stmt.syntax(),
+            Stmt::Return(stmt) => stmt.syntax(),
+            Stmt::Break(stmt) => stmt.syntax(),
+            Stmt::Continue(stmt) => stmt.syntax(),
+            Stmt::Block(stmt) => stmt.syntax(),
+            Stmt::Struct(stmt) => stmt.syntax(),
+            Stmt::Function(stmt) => stmt.syntax(),
+            Stmt::Match(stmt) => stmt.syntax(),
+            Stmt::Import(stmt) => stmt.syntax(),
+            Stmt::Expr(stmt) => stmt.syntax(),
+        }
+    }
+}
+
+// an `ERROR` node in expression position stands for an expression that couldn't be parsed
+#[derive(Clone)]
+pub enum Expr {
+    Literal(Literal),
+    Name(NameRef),
+    Path(PathExpr),
+    Binary(BinaryExpr),
+    Paren(ParenExpr),
+    Unary(UnaryExpr),
+    Array(ArrayExpr),
+    Index(IndexExpr),
+    Assign(AssignExpr),
+    Member(MemberExpr),
+    Call(CallExpr),
+    StructLiteral(StructLiteral),
+    Conditional(ConditionalExpr),
+    Error(ErrorNode),
+}
+
+impl AstNode for Expr {
+    fn cast(node: SyntaxNode) -> Option<Expr> {
+        let expr = match node.kind() {
+            SyntaxKind::LITERAL => Expr::Literal(Literal(node)),
+            SyntaxKind::NAME_REF => Expr::Name(NameRef(node)),
+            SyntaxKind::PATH_EXPR => Expr::Path(PathExpr(node)),
+            SyntaxKind::BINARY_EXPR => Expr::Binary(BinaryExpr(node)),
+            SyntaxKind::PAREN_EXPR => Expr::Paren(ParenExpr(node)),
+            SyntaxKind::UNARY_EXPR => Expr::Unary(UnaryExpr(node)),
+            SyntaxKind::ARRAY_EXPR => Expr::Array(ArrayExpr(node)),
+            SyntaxKind::INDEX_EXPR => Expr::Index(IndexExpr(node)),
+            SyntaxKind::ASSIGN_EXPR => Expr::Assign(AssignExpr(node)),
+            SyntaxKind::MEMBER_EXPR => Expr::Member(MemberExpr(node)),
+            SyntaxKind::CALL_EXPR => Expr::Call(CallExpr(node)),
+            SyntaxKind::STRUCT_LITERAL => Expr::StructLiteral(StructLiteral(node)),
+            SyntaxKind::CONDITIONAL_EXPR => Expr::Conditional(ConditionalExpr(node)),
+            SyntaxKind::ERROR => Expr::Error(ErrorNode(node)),
+            _ => return None,
+        };
+        Some(expr)
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Expr::Literal(expr) => expr.syntax(),
+            Expr::Name(expr) => expr.syntax(),
+            Expr::Path(expr) => expr.syntax(),
+            Expr::Binary(expr) => expr.syntax(),
+            Expr::Paren(expr) => expr.syntax(),
+            Expr::Unary(expr) => expr.syntax(),
+            Expr::Array(expr) => expr.syntax(),
+            Expr::Index(expr) => expr.syntax(),
+            Expr::Assign(expr) => expr.syntax(),
+            Expr::Member(expr) => expr.syntax(),
+            Expr::Call(expr) => expr.syntax(),
+            Expr::StructLiteral(expr) => expr.syntax(),
+            Expr::Conditional(expr) => expr.syntax(),
+            Expr::Error(expr) => expr.syntax(),
+        }
+    }
+}
+
+// the direct children of `node` that are an `N`
+fn children<N: AstNode>(node: &SyntaxNode) -> Vec<N> {
+    node.children().into_iter().filter_map(N::cast).collect()
+}
+
+fn child<N: AstNode>(node: &SyntaxNode) -> Option<N> {
+    node.children().into_iter().find_map(N::cast)
+}
+
+// the child node at `index`, the parser puts the operands of a construct in a fixed order
+fn nth_expr(node: &SyntaxNode, index: usize) -> Option<Expr> {
+    node.children().into_iter().nth(index).and_then(Expr::cast)
+}
+
+// whitespace, comments and bytes the tokenizer skipped
+fn is_trivia(element: &SyntaxElement) -> bool {
+    match element {
+        SyntaxElement::Token(token) => token.kind().is_trivia() || token.kind() == SyntaxKind::ERROR,
+        SyntaxElement::Node(_) => false,
+    }
+}
+
+// the first token directly under `node` that is one of `kinds`
+fn token(node: &SyntaxNode, kinds: &[SyntaxKind]) -> Option<SyntaxToken> {
+    node.children_with_tokens().into_iter().find_map(|child| match child {
+        SyntaxElement::Token(token) if kinds.contains(&token.kind()) => Some(token),
+        _ => None,
+    })
+}
+
+// the first token directly under `node` that isn't trivia
+fn first_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.children_with_tokens().into_iter().find_map(|child| match child {
+        SyntaxElement::Token(token) if !is_trivia(&SyntaxElement::Token(token.clone())) => Some(token),
+        _ => None,
+    })
+}
+
+impl Program {
+    pub fn statements(&self) -> Vec<Stmt> {
+        children(&self.0)
+    }
+}
+
+impl LetStmt {
+    // `let` or `const`
+    pub fn keyword(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+
+    pub fn is_constant(&self) -> bool {
+        self.keyword().is_some_and(|keyword| keyword.text() == "const")
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, &[SyntaxKind::IDENTIFIER])
+    }
+
+    pub fn annotation(&self) -> Option<TypeRef> {
+        child(&self.0)
+    }
+
+    // a token that can't be a name is skipped into an error node right after the
+    // keyword, that node is never the initializer
+    pub fn initializer(&self) -> Option<Expr> {
+        let mut elements = self.0.children_with_tokens().into_iter().filter(|child| !is_trivia(child)).skip(1);
+        let skipped = match elements.next() {
+            Some(SyntaxElement::Node(node)) if node.kind() == SyntaxKind::ERROR && !node.text_range().is_empty() => {
+                Some(node.text_range())
+            }
+            _ => None,
+        };
+        let last = self.0.children().into_iter().filter(|node| node.kind() != SyntaxKind::TYPE).last()?;
+        match Some(last.text_range()) == skipped {
+            true => None,
+            false => Expr::cast(last),
+        }
+    }
+}
+
+impl IfStmt {
+    pub fn condition(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0)
+    }
+}
+
+impl WhileStmt {
+    pub fn condition(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0)
+    }
+}
+
+impl ReturnStmt {
+    pub fn keyword(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+}
+
+impl BreakStmt {
+    pub fn keyword(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+}
+
+impl ContinueStmt {
+    pub fn keyword(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+}
+
+impl Block {
+    pub fn statements(&self) -> Vec<Stmt> {
+        children(&self.0)
+    }
+}
+
+impl StructDecl {
+    pub fn keyword(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, &[SyntaxKind::IDENTIFIER])
+    }
+
+    pub fn fields(&self) -> Vec<FieldDecl> {
+        children(&self.0)
+    }
+}
+
+impl FieldDecl {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, &[SyntaxKind::IDENTIFIER])
+    }
+
+    pub fn type_ref(&self) -> Option<TypeRef> {
+        child(&self.0)
+    }
+}
+
+impl FunctionDecl {
+    pub fn keyword(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, &[SyntaxKind::IDENTIFIER])
+    }
+
+    pub fn param_list(&self) -> Option<ParamList> {
+        child(&self.0)
+    }
+
+    pub fn return_type(&self) -> Option<TypeRef> {
+        child(&self.0)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0)
+    }
+}
+
+impl ParamList {
+    pub fn params(&self) -> Vec<Param> {
+        children(&self.0)
+    }
+}
+
+impl Param {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, &[SyntaxKind::IDENTIFIER])
+    }
+
+    pub fn type_ref(&self) -> Option<TypeRef> {
+        child(&self.0)
+    }
+}
+
+impl TypeRef {
+    // a builtin type keyword or the name of a struct
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, &[SyntaxKind::KEYWORD, SyntaxKind::IDENTIFIER])
+    }
+}
+
+impl MatchStmt {
+    pub fn scrutinee(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+
+    pub fn arms(&self) -> Vec<MatchArm> {
+        children(&self.0)
+    }
+}
+
+impl MatchArm {
+    pub fn pattern(&self) -> Option<PatternNode> {
+        child(&self.0)
+    }
+
+    // a block or a single expression
+    pub fn body(&self) -> Option<SyntaxNode> {
+        self.0.children().into_iter().filter(|node| node.kind() != SyntaxKind::PATTERN).last()
+    }
+}
+
+impl PatternNode {
+    // a literal or a name, a token that isn't a pattern is kept in an error node instead
+    pub fn token(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+}
+
+impl ImportStmt {
+    pub fn path(&self) -> Option<SyntaxToken> {
+        token(&self.0, &[SyntaxKind::STRING])
+    }
+}
+
+impl ExprStmt {
+    pub fn expr(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+}
+
+impl Literal {
+    pub fn token(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+}
+
+impl NameRef {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, &[SyntaxKind::IDENTIFIER])
+    }
+}
+
+impl PathExpr {
+    // one entry per segment, `None` where the segment after a `::` is missing
+    pub fn segments(&self) -> Vec<Option<SyntaxToken>> {
+        let mut segments = Vec::new();
+        for child in self.0.children_with_tokens() {
+            let SyntaxElement::Token(token) = child else { continue };
+            match token.kind() {
+                SyntaxKind::IDENTIFIER if segments.is_empty() => segments.push(Some(token)),
+                SyntaxKind::IDENTIFIER => *segments.last_mut().unwrap() = Some(token),
+                SyntaxKind::DCOLON => segments.push(None),
+                _ => {}
+            }
+        }
+        segments
+    }
+}
+
+impl BinaryExpr {
+    pub fn lhs(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+
+    pub fn operator(&self) -> Option<SyntaxToken> {
+        first_token(&self.0)
+    }
+
+    pub fn rhs(&self) -> Option<Expr> {
+        nth_expr(&self.0, 1)
+    }
+}
+
+impl ParenExpr {
+    pub fn inner(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+}
+
+impl UnaryExpr {
+    pub fn operand(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+}
+
+impl ArrayExpr {
+    pub fn elements(&self) -> Vec<Expr> {
+        children(&self.0)
+    }
+}
+
+impl IndexExpr {
+    pub fn base(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+
+    pub fn index(&self) -> Option<Expr> {
+        nth_expr(&self.0, 1)
+    }
+}
+
+impl AssignExpr {
+    pub fn target(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        nth_expr(&self.0, 1)
+    }
+}
+
+impl MemberExpr {
+    pub fn base(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, &[SyntaxKind::IDENTIFIER])
+    }
+}
+
+impl CallExpr {
+    pub fn callee(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+
+    pub fn arg_list(&self) -> Option<ArgList> {
+        child(&self.0)
+    }
+}
+
+impl ArgList {
+    pub fn args(&self) -> Vec<Expr> {
+        children(&self.0)
+    }
+}
+
+impl StructLiteral {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, &[SyntaxKind::IDENTIFIER])
+    }
+
+    pub fn fields(&self) -> Vec<FieldInit> {
+        children(&self.0)
+    }
+}
+
+impl FieldInit {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, &[SyntaxKind::IDENTIFIER])
+    }
+
+    // a skipped name comes first, the value is always the last node
+    pub fn value(&self) -> Option<Expr> {
+        self.0.children().into_iter().last().and_then(Expr::cast)
+    }
+}
+
+impl ConditionalExpr {
+    pub fn condition(&self) -> Option<Expr> {
+        nth_expr(&self.0, 0)
+    }
+
+    pub fn then_branch(&self) -> Option<Expr> {
+        nth_expr(&self.0, 1)
+    }
+
+    pub fn else_branch(&self) -> Option<Expr> {
+        nth_expr(&self.0, 2)
+    }
+}
+
+// the program spelled by `root`, the tree `build_tree` made from `source` and `tokens`
+pub fn lower(source: &str, tokens: &[Token], root: &SyntaxNode) -> Vec<parser::Stmt> {
+    let lowering = Lowering { tokens, ranges: token_ranges(source, tokens) };
+    match Program::cast(root.clone()) {
+        Some(program) => lowering.statements(program.statements()),
+        None => Vec::new(),
+    }
+}
+
+struct Lowering<'a> {
+    tokens: &'a [Token],
+    ranges: Vec<Range<usize>>,
+}
+
+impl<'a> Lowering<'a> {
+    // index of the first token starting at or after `offset`, EOF if there is none
+    fn index(&self, offset: usize) -> usize {
+        self.ranges.partition_point(|range| range.start < offset)
+    }
+
+    fn token(&self, token: &SyntaxToken) -> &'a Token {
+        &self.tokens[self.index(token.text_range().start)]
+    }
+
+    // the span of the token at `offset`, which is where the parser reports a part that is missing
+    fn span_at(&self, offset: usize) -> Span {
+        self.tokens[self.index(offset)].span()
+    }
+
+    // the tokens inside `range`, or the token after it when there are none
+    fn span_of(&self, range: Range<usize>) -> Span {
+        let (first, end) = (self.index(range.start), self.index(range.end));
+        match first < end {
+            true => self.tokens[first].span().to(self.tokens[end - 1].span()),
+            false => self.span_at(range.start),
+        }
+    }
+
+    // a missing name is empty and takes the span of the token in its place
+    fn name(&self, name: Option<SyntaxToken>, offset: usize) -> (String, Span) {
+        let token = match name {
+            Some(name) => self.token(&name),
+            None => return (String::new(), self.span_at(offset)),
+        };
+        match &token.kind {
+            TokenKind::IDENTIFIER(name) => (name.to_string(), token.span()),
+            _ => (String::new(), token.span()),
+        }
+    }
+
+    fn type_annotation(&self, type_ref: Option<TypeRef>, offset: usize) -> TypeAnnotation {
+        let offset = type_ref.as_ref().map_or(offset, |type_ref| type_ref.syntax().text_range().start);
+        let name = match type_ref.and_then(|type_ref| type_ref.name()).map(|name| &self.token(&name).kind) {
+            Some(TokenKind::KEYWORD(name) | TokenKind::IDENTIFIER(name)) => name.to_string(),
+            _ => String::new(),
+        };
+        TypeAnnotation { name, span: self.span_at(offset) }
+    }
+
+    // statements that failed to parse are left out, an import without a path among them
+    fn statements(&self, statements: Vec<Stmt>) -> Vec<parser::Stmt> {
+        statements.into_iter().filter_map(|stmt| self.stmt(stmt)).collect()
+    }
+
+    fn block(&self, block: Option<Block>) -> parser::Stmt {
+        parser::Stmt::Block(block.map_or_else(Vec::new, |block| self.statements(block.statements())))
+    }
+
+    fn keyword_span(&self, keyword: Option<SyntaxToken>, node: &SyntaxNode) -> Span {
+        match keyword {
+            Some(keyword) => self.token(&keyword).span(),
+            None => self.span_at(node.text_range().start),
+        }
+    }
+
+    fn stmt(&self, stmt: Stmt) -> Option<parser::Stmt> {
+        let stmt = match stmt {
+            Stmt::Let(stmt) => {
+                let after_keyword = stmt.keyword().map_or(stmt.syntax().text_range().start, |keyword| keyword.text_range().end);
+                let (name, span) = self.name(stmt.name(), after_keyword);
+                let annotation = stmt.annotation().map(|type_ref| self.type_annotation(Some(type_ref), after_keyword));
+                parser::Stmt::VariableDeclaration(VariableDeclaration {
+                    name,
+                    span,
+                    constant: stmt.is_constant(),
+                    annotation,
+                    initializer: stmt.initializer().map(|initializer| self.expr(Some(initializer))),
+                })
+            }
+            Stmt::If(stmt) => parser::Stmt::IFSTATEMENT(self.expr(stmt.condition()), Box::new(self.block(stmt.body()))),
+            Stmt::While(stmt) => parser::Stmt::WHILE(self.expr(stmt.condition()), Box::new(self.block(stmt.body()))),
+            Stmt::Return(stmt) => {
+                let span = self.keyword_span(stmt.keyword(), stmt.syntax());
+                parser::Stmt::RETURN(stmt.value().map(|value| self.expr(Some(value))), span)
+            }
+            Stmt::Break(stmt) => parser::Stmt::BREAK(self.keyword_span(stmt.keyword(), stmt.syntax())),
+            Stmt::Continue(stmt) => parser::Stmt::CONTINUE(self.keyword_span(stmt.keyword(), stmt.syntax())),
+            Stmt::Block(block) => self.block(Some(block)),
+            Stmt::Struct(stmt) => {
+                let after_keyword = stmt.keyword().map_or(stmt.syntax().text_range().start, |keyword| keyword.text_range().end);
+                let (name, _) = self.name(stmt.name(), after_keyword);
+                let fields = stmt
+                    .fields()
+                    .into_iter()
+                    .map(|field| {
+                        let start = field.syntax().text_range().start;
+                        let (name, span) = self.name(field.name(), start);
+                        let type_name = self.type_annotation(field.type_ref(), field.syntax().text_range().end);
+                        FieldDeclaration { name, type_name: type_name.name, span: span.to(type_name.span) }
+                    })
+                    .collect();
+                parser::Stmt::StructDeclaration(name, fields)
+            }
+            Stmt::Function(stmt) => {
+                let range = stmt.syntax().text_range();
+                let after_keyword = stmt.keyword().map_or(range.start, |keyword| keyword.text_range().end);
+                let (name, span) = self.name(stmt.name(), after_keyword);
+                let body = stmt.body();
+                let signature_end = body.as_ref().map_or(range.end, |body| body.syntax().text_range().start);
+                let parameters = stmt
+                    .param_list()
+                    .map(|list| list.params())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|parameter| {
+                        let range = parameter.syntax().text_range();
+                        let (name, span) = self.name(parameter.name(), range.start);
+                        Parameter { name, span, annotation: self.type_annotation(parameter.type_ref(), range.end) }
+                    })
+                    .collect();
+                parser::Stmt::FunctionDeclaration(FunctionDeclaration {
+                    name,
+                    span,
+                    signature: self.span_of(range.start..signature_end),
+                    parameters,
+                    return_type: stmt.return_type().map(|type_ref| self.type_annotation(Some(type_ref), signature_end)),
+                    body: Box::new(self.block(body)),
+                })
+            }
+            Stmt::Match(stmt) => {
+                let arms = stmt.arms().into_iter().map(|arm| self.match_arm(arm)).collect();
+                parser::Stmt::MATCH(self.expr(stmt.scrutinee()), arms)
+            }
+            Stmt::Import(stmt) => {
+                let token = self.token(&stmt.path()?);
+                match &token.kind {
+                    TokenKind::STRING(path) => parser::Stmt::IMPORT(path.to_string(), token.span()),
+                    _ => return None,
+                }
+            }
+            Stmt::Expr(stmt) => {
+                let expr = stmt.expr();
+                let span = self.span_of(expr.as_ref().map_or(stmt.syntax().text_range(), |expr| expr.syntax().text_range()));
+                parser::Stmt::EXPR(self.expr(expr), span)
+            }
+        };
+        Some(stmt)
+    }
+
+    fn match_arm(&self, arm: MatchArm) -> parser::MatchArm {
+        let pattern = arm.pattern();
+        let offset = pattern.as_ref().map_or(arm.syntax().text_range().start, |pattern| pattern.syntax().text_range().start);
+        let span = self.span_at(offset);
+        let token = pattern.and_then(|pattern| pattern.token()).map(|token| self.token(&token));
+        let pattern = match token.map(|token| &token.kind) {
+            Some(TokenKind::INT(Some(value))) => Pattern::Literal(parser::Expr::INTEGERLITERAL(*value, span), span),
+            Some(TokenKind::FLOAT(value)) => Pattern::Literal(parser::Expr::FLOATLITERAL(*value, span), span),
+            Some(TokenKind::STRING(value)) => Pattern::Literal(parser::Expr::STRINGLITERAL(value.to_string(), span), span),
+            Some(TokenKind::KEYWORD(keyword)) if keyword == "true" || keyword == "false" => {
+                Pattern::Literal(parser::Expr::BOOLLITERAL(keyword == "true", span), span)
+            }
+            Some(TokenKind::IDENTIFIER(name)) if name == "_" => Pattern::Wildcard(span),
+            Some(TokenKind::IDENTIFIER(name)) => Pattern::Binding(name.to_string(), span),
+            _ => Pattern::Literal(parser::Expr::Error, span),
+        };
+
+        let body = match arm.body() {
+            Some(body) if body.kind() == SyntaxKind::BLOCK => self.block(Block::cast(body)),
+            Some(body) => parser::Stmt::EXPR(self.expr(Expr::cast(body.clone())), self.span_of(body.text_range())),
+            None => parser::Stmt::EXPR(parser::Expr::Error, self.span_at(arm.syntax().text_range().end)),
+        };
+        parser::MatchArm { pattern, body }
+    }
+
+    fn boxed(&self, expr: Option<Expr>) -> Box<parser::Expr> {
+        Box::new(self.expr(expr))
+    }
+
+    fn exprs(&self, exprs: Vec<Expr>) -> Vec<parser::Expr> {
+        exprs.into_iter().map(|expr| self.expr(Some(expr))).collect()
+    }
+
+    // a part that is missing altogether lowers to `Expr::Error`, like the parser's placeholder
+    fn expr(&self, expr: Option<Expr>) -> parser::Expr {
+        let Some(expr) = expr else { return parser::Expr::Error };
+        match expr {
+            Expr::Literal(literal) => {
+                let Some(token) = literal.token().map(|token| self.token(&token)) else { return parser::Expr::Error };
+                match &token.kind {
+                    TokenKind::INT(Some(value)) => parser::Expr::INTEGERLITERAL(*value, token.span()),
+                    TokenKind::FLOAT(value) => parser::Expr::FLOATLITERAL(*value, token.span()),
+                    TokenKind::STRING(value) => parser::Expr::STRINGLITERAL(value.to_string(), token.span()),
+                    TokenKind::KEYWORD(keyword) => parser::Expr::BOOLLITERAL(keyword == "true", token.span()),
+                    _ => parser::Expr::Error,
+                }
+            }
+            Expr::Name(name) => {
+                let (name, span) = self.name(name.name(), name.syntax().text_range().start);
+                parser::Expr::IDENTIFIER(name, span)
+            }
+            Expr::Path(path) => {
+                let end = path.syntax().text_range().end;
+                parser::Expr::PATH(path.segments().into_iter().map(|segment| self.name(segment, end).0).collect())
+            }
+            Expr::Binary(binary) => {
+                let operator = match binary.operator() {
+                    Some(operator) => self.token(&operator),
+                    None => &self.tokens[self.index(binary.syntax().text_range().end)],
+                };
+                let (lhs, rhs) = (self.boxed(binary.lhs()), self.boxed(binary.rhs()));
+                parser::Expr::BINARYEXPR(lhs, rhs, operator.kind.clone(), operator.span())
+            }
+            Expr::Paren(paren) => parser::Expr::PARENTHESIZED(self.boxed(paren.inner())),
+            Expr::Unary(unary) => parser::Expr::UNARY(self.boxed(unary.operand())),
+            Expr::Array(array) => parser::Expr::ARRAY(self.exprs(array.elements())),
+            Expr::Index(index) => parser::Expr::INDEX(self.boxed(index.base()), self.boxed(index.index())),
+            Expr::Assign(assign) => parser::Expr::ASSIGNMENT(self.boxed(assign.target()), self.boxed(assign.value())),
+            Expr::Member(member) => {
+                let (name, _) = self.name(member.name(), member.syntax().text_range().end);
+                parser::Expr::MEMBER(self.boxed(member.base()), name)
+            }
+            Expr::Call(call) => {
+                let arguments = call.arg_list().map(|list| list.args()).unwrap_or_default();
+                parser::Expr::CALL(self.boxed(call.callee()), self.exprs(arguments))
+            }
+            Expr::StructLiteral(literal) => {
+                let (name, _) = self.name(literal.name(), literal.syntax().text_range().start);
+                let fields = literal
+                    .fields()
+                    .into_iter()
+                    .map(|field| {
+                        let (name, span) = self.name(field.name(), field.syntax().text_range().start);
+                        FieldInitializer { name, value: self.expr(field.value()), span }
+                    })
+                    .collect();
+                parser::Expr::STRUCTLITERAL(name, fields)
+            }
+            Expr::Conditional(conditional) => parser::Expr::CONDITIONAL(
+                self.boxed(conditional.condition()),
+                self.boxed(conditional.then_branch()),
+                self.boxed(conditional.else_branch()),
+            ),
+            Expr::Error(_) => parser::Expr::Error,
+        }
+    }
+}
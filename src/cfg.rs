@@ -0,0 +1,356 @@
+// control flow graph.
+//
+// `Cfg::build` lowers a list of statements, the whole program or the body of a
+// function, into basic blocks: straight runs of statements that always execute
+// together, ended by a `Terminator` that says what is evaluated to decide where
+// to go next. where control actually goes is recorded as labelled `Edge`s, so
+// loops, `break`, `continue` and `return` all show up as explicit edges.
+//
+// a function declaration is an ordinary statement of the graph it appears in,
+// its body gets a graph of its own.
+//
+// every graph has an empty `entry` block it starts in and an `exit` block that
+// `return` and the end of the statements lead to. statements after a jump end up
+// in a block without predecessors, which is how dead code looks in the graph.
+
+use std::fmt::Write;
+
+use crate::arena::{Ast, ExprId, PatternNode, StmtId, StmtNode};
+use crate::tokenizer::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Terminator {
+    // goes on along its only edge, or nowhere for a block that was never finished
+    Jump,
+    // the condition of an `if` or `while`, followed by a `True` and a `False` edge
+    Branch(ExprId),
+    // the scrutinee of a `match`, followed by one `Arm` edge per arm
+    Match(ExprId),
+    Return(Option<ExprId>),
+    // the exit block
+    Exit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Next,
+    True,
+    False,
+    // to the body of the arm with this index
+    Arm(usize),
+    // past a `match` without a catch-all arm when no arm matched
+    NoMatch,
+    // from the end of a loop body back to its condition
+    Back,
+    Break,
+    Continue,
+    Return,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: BlockId,
+    pub to: BlockId,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    // only statements that don't affect control flow, in execution order
+    pub statements: Vec<StmtId>,
+    pub terminator: Terminator,
+}
+
+#[derive(Debug)]
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+    pub edges: Vec<Edge>,
+    pub entry: BlockId,
+    pub exit: BlockId,
+}
+
+impl Cfg {
+    pub fn from_program(ast: &Ast) -> Cfg {
+        Cfg::build(ast, ast.program())
+    }
+
+    pub fn build(ast: &Ast, statements: &[StmtId]) -> Cfg {
+        let mut builder = Builder { ast, blocks: Vec::new(), edges: Vec::new(), current: None, loops: Vec::new() };
+        let entry = builder.new_block();
+        let exit = builder.new_block();
+        builder.blocks[exit.0].terminator = Terminator::Exit;
+
+        builder.current = Some(entry);
+        for stmt in statements {
+            builder.stmt(*stmt, exit);
+        }
+        builder.goto(exit, EdgeKind::Next);
+
+        Cfg { blocks: builder.blocks, edges: builder.edges, entry, exit }
+    }
+
+    pub fn block(&self, id: BlockId) -> &BasicBlock {
+        &self.blocks[id.0]
+    }
+
+    pub fn successors(&self, id: BlockId) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.from == id)
+    }
+
+    pub fn predecessors(&self, id: BlockId) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.to == id)
+    }
+
+    // whether each block can be reached from the entry, by `BlockId`
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut pending = vec![self.entry];
+        while let Some(block) = pending.pop() {
+            if std::mem::replace(&mut reachable[block.0], true) {
+                continue;
+            }
+            pending.extend(self.successors(block).map(|edge| edge.to));
+        }
+        reachable
+    }
+
+    // the graph in graphviz format, `dot -Tsvg` draws it
+    pub fn to_dot(&self, ast: &Ast) -> String {
+        let mut dot = String::from("digraph cfg {\n    node [shape=box, fontname=monospace];\n");
+        for (index, block) in self.blocks.iter().enumerate() {
+            let mut lines = vec![format!("b{}", index)];
+            if BlockId(index) == self.entry {
+                lines[0].push_str(" (entry)");
+            }
+            lines.extend(block.statements.iter().map(|stmt| describe(ast, *stmt)));
+            match block.terminator {
+                Terminator::Jump => {}
+                Terminator::Branch(condition) => lines.push(format!("branch{}", line(ast.span(condition)))),
+                Terminator::Match(scrutinee) => lines.push(format!("match{}", line(ast.span(scrutinee)))),
+                Terminator::Return(value) => {
+                    lines.push(format!("return{}", line(value.and_then(|value| ast.span(value)))))
+                }
+                Terminator::Exit => lines.push(String::from("exit")),
+            }
+            let label: Vec<String> = lines.iter().map(|line| line.replace('\\', "\\\\").replace('"', "\\\"")).collect();
+            let _ = writeln!(dot, "    b{} [label=\"{}\\l\"];", index, label.join("\\l"));
+        }
+        for edge in &self.edges {
+            let label = match edge.kind {
+                EdgeKind::Next => String::new(),
+                EdgeKind::True => String::from("true"),
+                EdgeKind::False => String::from("false"),
+                EdgeKind::Arm(index) => format!("arm {}", index),
+                EdgeKind::NoMatch => String::from("no match"),
+                EdgeKind::Back => String::from("loop"),
+                EdgeKind::Break => String::from("break"),
+                EdgeKind::Continue => String::from("continue"),
+                EdgeKind::Return => String::from("return"),
+            };
+            let _ = match label.is_empty() {
+                true => writeln!(dot, "    b{} -> b{};", edge.from.0, edge.to.0),
+                false => writeln!(dot, "    b{} -> b{} [label=\"{}\"];", edge.from.0, edge.to.0, label),
+            };
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// ` (line 3)`, or nothing without a span
+fn line(span: Option<Span>) -> String {
+    span.map(|span| format!(" (line {})", span.start_line)).unwrap_or_default()
+}
+
+// a one line summary of a statement kept in a block
+fn describe(ast: &Ast, stmt: StmtId) -> String {
+    let text = match *ast.stmt(stmt) {
+        StmtNode::VariableDeclaration(declaration) => {
+            let keyword = if declaration.constant { "const" } else { "let" };
+            format!("{} {}", keyword, ast.name(declaration.name))
+        }
+        StmtNode::StructDeclaration(name, _) => format!("struct {}", ast.name(name)),
+        StmtNode::FunctionDeclaration(function) => format!("function {}", ast.name(function.name)),
+        StmtNode::IMPORT(path, _) => format!("import \"{}\"", ast.name(path)),
+        _ => String::from("expression"),
+    };
+    format!("{}{}", text, line(ast.stmt_span(stmt)))
+}
+
+struct Builder<'a> {
+    ast: &'a Ast,
+    blocks: Vec<BasicBlock>,
+    edges: Vec<Edge>,
+    // the block statements are added to, `None` right after a jump
+    current: Option<BlockId>,
+    // the condition block and the block after the loop for every loop around the current statement
+    loops: Vec<(BlockId, BlockId)>,
+}
+
+impl Builder<'_> {
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(BasicBlock { statements: Vec::new(), terminator: Terminator::Jump });
+        BlockId(self.blocks.len() - 1)
+    }
+
+    // the block to add to, code after a jump starts a block nothing leads to
+    fn block(&mut self) -> BlockId {
+        match self.current {
+            Some(block) => block,
+            None => {
+                let block = self.new_block();
+                self.current = Some(block);
+                block
+            }
+        }
+    }
+
+    fn edge(&mut self, from: BlockId, to: BlockId, kind: EdgeKind) {
+        self.edges.push(Edge { from, to, kind });
+    }
+
+    // ends the current block with a jump to `to`
+    fn goto(&mut self, to: BlockId, kind: EdgeKind) {
+        if let Some(block) = self.current.take() {
+            self.edge(block, to, kind);
+        }
+    }
+
+    // ends the current block with `terminator`, returns the ended block
+    fn terminate(&mut self, terminator: Terminator) -> BlockId {
+        let block = self.block();
+        self.blocks[block.0].terminator = terminator;
+        self.current = None;
+        block
+    }
+
+    fn stmt(&mut self, stmt: StmtId, exit: BlockId) {
+        match *self.ast.stmt(stmt) {
+            StmtNode::EOF => {}
+            StmtNode::VariableDeclaration(_)
+            | StmtNode::StructDeclaration(_, _)
+            | StmtNode::FunctionDeclaration(_)
+            | StmtNode::IMPORT(_, _)
+            | StmtNode::EXPR(_, _) => {
+                let block = self.block();
+                self.blocks[block.0].statements.push(stmt);
+            }
+            StmtNode::Block(statements) => {
+                for stmt in self.ast.stmt_list(statements) {
+                    self.stmt(*stmt, exit);
+                }
+            }
+            StmtNode::IFSTATEMENT(condition, body) => {
+                let branch = self.terminate(Terminator::Branch(condition));
+                let then = self.new_block();
+                let join = self.new_block();
+                self.edge(branch, then, EdgeKind::True);
+                self.edge(branch, join, EdgeKind::False);
+
+                self.current = Some(then);
+                self.stmt(body, exit);
+                self.goto(join, EdgeKind::Next);
+                self.current = Some(join);
+            }
+            StmtNode::WHILE(condition, body) => {
+                let header = self.new_block();
+                self.goto(header, EdgeKind::Next);
+                self.blocks[header.0].terminator = Terminator::Branch(condition);
+                let start = self.new_block();
+                let after = self.new_block();
+                self.edge(header, start, EdgeKind::True);
+                self.edge(header, after, EdgeKind::False);
+
+                self.loops.push((header, after));
+                self.current = Some(start);
+                self.stmt(body, exit);
+                self.goto(header, EdgeKind::Back);
+                self.loops.pop();
+                self.current = Some(after);
+            }
+            // outside of a loop these are errors reported by `flow`, they end the statements here
+            StmtNode::BREAK(_) => {
+                let target = self.loops.last().map(|(_, after)| *after).unwrap_or(exit);
+                self.block();
+                self.goto(target, EdgeKind::Break);
+            }
+            StmtNode::CONTINUE(_) => {
+                let target = self.loops.last().map(|(header, _)| *header).unwrap_or(exit);
+                self.block();
+                self.goto(target, EdgeKind::Continue);
+            }
+            StmtNode::RETURN(value, _) => {
+                let block = self.terminate(Terminator::Return(value));
+                self.edge(block, exit, EdgeKind::Return);
+            }
+            StmtNode::MATCH(scrutinee, arms) => {
+                let branch = self.terminate(Terminator::Match(scrutinee));
+                let join = self.new_block();
+                let arms = self.ast.match_arms(arms);
+                for (index, arm) in arms.iter().enumerate() {
+                    let start = self.new_block();
+                    self.edge(branch, start, EdgeKind::Arm(index));
+                    self.current = Some(start);
+                    self.stmt(arm.body, exit);
+                    self.goto(join, EdgeKind::Next);
+                }
+                if arms.iter().all(|arm| matches!(arm.pattern, PatternNode::Literal(_, _))) {
+                    self.edge(branch, join, EdgeKind::NoMatch);
+                }
+                self.current = Some(join);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{resolved, Resolved};
+
+    #[test]
+    fn test_control_flow_graph_has_explicit_edges() {
+        let source = "let i = 0;\nwhile (i < 10) {\n    if (i == 5) {\n        break;\n    }\n    i = i + 1;\n    continue;\n    i = 0;\n}\nmatch (i) { 1 => i, 2 => { return i; } }\nlet done = true;";
+        let Resolved { ast, .. } = resolved(source);
+        let cfg = Cfg::from_program(&ast);
+
+        let edges: Vec<(usize, usize, EdgeKind)> = cfg.edges.iter().map(|edge| (edge.from.0, edge.to.0, edge.kind)).collect();
+        assert_eq!(
+            edges,
+            [
+                (0, 2, EdgeKind::Next),
+                (2, 3, EdgeKind::True),
+                (2, 4, EdgeKind::False),
+                (3, 5, EdgeKind::True),
+                (3, 6, EdgeKind::False),
+                (5, 4, EdgeKind::Break),
+                (6, 2, EdgeKind::Continue),
+                (7, 2, EdgeKind::Back),
+                (4, 9, EdgeKind::Arm(0)),
+                (9, 8, EdgeKind::Next),
+                (4, 10, EdgeKind::Arm(1)),
+                (10, 1, EdgeKind::Return),
+                (4, 8, EdgeKind::NoMatch),
+                (8, 1, EdgeKind::Next),
+            ]
+        );
+        assert_eq!(cfg.block(BlockId(2)).terminator, Terminator::Branch(match *ast.stmt(ast.program()[1]) {
+            StmtNode::WHILE(condition, _) => condition,
+            ref other => panic!("expected a loop, got {:?}", other),
+        }));
+        assert!(matches!(cfg.block(cfg.exit).terminator, Terminator::Exit));
+        // `i = 0;` after the `continue` is in a block nothing leads to
+        assert_eq!(cfg.block(BlockId(7)).statements.len(), 1);
+        assert_eq!(cfg.reachable().iter().filter(|reachable| !**reachable).count(), 1);
+        assert_eq!(cfg.predecessors(BlockId(2)).count(), 3);
+
+        let dot = cfg.to_dot(&ast);
+        assert!(dot.starts_with("digraph cfg {\n"));
+        assert!(dot.contains("    b0 [label=\"b0 (entry)\\llet i (line 1)\\l\"];\n"));
+        assert!(dot.contains("    b5 -> b4 [label=\"break\"];\n"));
+        assert!(dot.contains("    b10 [label=\"b10\\lreturn (line 10)\\l\"];\n"));
+    }
+}
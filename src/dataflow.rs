@@ -0,0 +1,204 @@
+// definite initialization.
+//
+// `check_initialized` finds reads of variables declared without a value, like
+// `let x: int;`, that can run before the variable is assigned. it is a forward
+// dataflow analysis over the control flow graph: every block starts with the
+// variables that may still be unassigned on some path into it, the union of
+// what its predecessors end with, and the blocks are revisited until nothing
+// changes. loops are handled by the `Back` edges of the graph. a variable is
+// only reported at its first such read on a path, after that it is treated as
+// assigned so the same mistake isn't reported on every line below it. the body
+// of a function is checked with its own graph, its parameters always have a value.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::arena::{Ast, ExprId, ExprNode, StmtId, StmtNode};
+use crate::cfg::{BlockId, Cfg, Terminator};
+use crate::error::SemanticError;
+use crate::resolver::{DeclarationId, Resolution};
+use crate::tokenizer::Span;
+
+// the variables that may not be assigned yet
+type State = HashSet<DeclarationId>;
+
+pub fn check_initialized(ast: &Ast, cfg: &Cfg, resolution: &Resolution) -> Vec<SemanticError> {
+    let mut analysis = Analysis {
+        ast,
+        resolution,
+        declarations: resolution
+            .declarations
+            .iter()
+            .enumerate()
+            .map(|(index, declaration)| (declaration.span, DeclarationId(index)))
+            .collect(),
+        errors: None,
+    };
+
+    // the state at the start of every block, `None` for blocks not reached yet
+    let mut states: Vec<Option<State>> = vec![None; cfg.blocks.len()];
+    states[cfg.entry.0] = Some(State::new());
+    let mut pending = vec![cfg.entry];
+    while let Some(block) = pending.pop() {
+        let mut state = states[block.0].clone().unwrap_or_default();
+        analysis.block(cfg, block, &mut state);
+        for edge in cfg.successors(block) {
+            let first = states[edge.to.0].is_none();
+            let next = states[edge.to.0].get_or_insert_with(State::new);
+            let before = next.len();
+            next.extend(state.iter().copied());
+            if first || next.len() != before {
+                pending.push(edge.to);
+            }
+        }
+    }
+
+    // the states are final, go through every reached block once more to report
+    analysis.errors = Some(Vec::new());
+    for (index, state) in states.into_iter().enumerate() {
+        if let Some(mut state) = state {
+            analysis.block(cfg, BlockId(index), &mut state);
+        }
+    }
+    analysis.errors.unwrap_or_default()
+}
+
+struct Analysis<'a> {
+    ast: &'a Ast,
+    resolution: &'a Resolution,
+    declarations: HashMap<Span, DeclarationId>,
+    // only collected once the states are final
+    errors: Option<Vec<SemanticError>>,
+}
+
+impl Analysis<'_> {
+    fn block(&mut self, cfg: &Cfg, block: BlockId, state: &mut State) {
+        let block = cfg.block(block);
+        for stmt in &block.statements {
+            self.stmt(*stmt, state);
+        }
+        match block.terminator {
+            Terminator::Branch(expr) | Terminator::Match(expr) | Terminator::Return(Some(expr)) => {
+                self.expr(expr, state)
+            }
+            Terminator::Jump | Terminator::Return(None) | Terminator::Exit => {}
+        }
+    }
+
+    fn stmt(&mut self, stmt: StmtId, state: &mut State) {
+        match *self.ast.stmt(stmt) {
+            StmtNode::VariableDeclaration(declaration) => {
+                if let Some(initializer) = declaration.initializer {
+                    self.expr(initializer, state);
+                }
+                // a declaration in a loop body starts over on every iteration
+                if let Some(id) = self.declarations.get(&declaration.span) {
+                    match declaration.initializer {
+                        Some(_) => state.remove(id),
+                        None => state.insert(*id),
+                    };
+                }
+            }
+            StmtNode::EXPR(expr, _) => self.expr(expr, state),
+            StmtNode::FunctionDeclaration(function) => {
+                // checked once, when reporting
+                if let Some(errors) = &mut self.errors {
+                    let body = Cfg::build(self.ast, std::slice::from_ref(&function.body));
+                    errors.extend(check_initialized(self.ast, &body, self.resolution));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // evaluates `expr` in order, reading and assigning variables on the way
+    fn expr(&mut self, expr: ExprId, state: &mut State) {
+        let ast = self.ast;
+        match *ast.expr(expr) {
+            ExprNode::IDENTIFIER(name, span) => {
+                let Some(id) = self.resolution.references.get(&span) else { return };
+                if state.remove(id) {
+                    if let Some(errors) = &mut self.errors {
+                        errors.push(SemanticError::PossiblyUninitialized {
+                            name: ast.name(name).to_string(),
+                            span,
+                            declaration: self.resolution.declaration(*id).span,
+                        });
+                    }
+                }
+            }
+            ExprNode::ASSIGNMENT(target, value) => {
+                self.expr(value, state);
+                match *ast.expr(target) {
+                    ExprNode::IDENTIFIER(_, span) => {
+                        if let Some(id) = self.resolution.references.get(&span) {
+                            state.remove(id);
+                        }
+                    }
+                    // `a[i] = ...` and `a.b = ...` read `a`
+                    _ => self.expr(target, state),
+                }
+            }
+            // only one of the branches runs, a variable is assigned after it if both assign it
+            ExprNode::CONDITIONAL(condition, then, otherwise) => {
+                self.expr(condition, state);
+                let mut other = state.clone();
+                self.expr(then, state);
+                self.expr(otherwise, &mut other);
+                state.extend(other);
+            }
+            ExprNode::BINARYEXPR(left, right, _, _) | ExprNode::INDEX(left, right) => {
+                self.expr(left, state);
+                self.expr(right, state);
+            }
+            ExprNode::PARENTHESIZED(inner) | ExprNode::UNARY(inner) | ExprNode::MEMBER(inner, _) => {
+                self.expr(inner, state)
+            }
+            ExprNode::ARRAY(elements) => {
+                for element in ast.expr_list(elements) {
+                    self.expr(*element, state);
+                }
+            }
+            ExprNode::CALL(callee, arguments) => {
+                self.expr(callee, state);
+                for argument in ast.expr_list(arguments) {
+                    self.expr(*argument, state);
+                }
+            }
+            ExprNode::STRUCTLITERAL(_, fields) => {
+                for field in ast.field_initializers(fields) {
+                    self.expr(field.value, state);
+                }
+            }
+            ExprNode::INTEGERLITERAL(_, _)
+            | ExprNode::FLOATLITERAL(_, _)
+            | ExprNode::STRINGLITERAL(_, _)
+            | ExprNode::BOOLLITERAL(_, _)
+            | ExprNode::PATH(_)
+            | ExprNode::Error => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{reported, resolved, Resolved};
+
+    #[test]
+    fn test_reads_of_possibly_uninitialized_variables_are_reported() {
+        let source = "let a: int;\nlet b: int;\nlet c = 1;\nmatch (c) {\n    1 => { a = 1; b = 1; },\n    _ => { a = 2; }\n}\nc = a + b;\nc = b;\nlet d: int;\nif (c > 0) {\n    d = 1;\n}\nc = d;\nlet e: int;\nwhile (c < 3) {\n    c = c + e;\n    e = 0;\n}\nlet f: int;\nf = 3;\nc = f;\nreturn c;\nc = a + d;";
+        let Resolved { ast, resolution, .. } = resolved(source);
+        let errors = check_initialized(&ast, &Cfg::from_program(&ast), &resolution);
+
+        assert_eq!(
+            reported(&errors),
+            [
+                "8: E0601 'b' is used before it is assigned on every path",
+                "14: E0601 'd' is used before it is assigned on every path",
+                "17: E0601 'e' is used before it is assigned on every path",
+            ]
+        );
+        assert_eq!(errors[0].related()[0].0.start_line, 2);
+        assert_eq!(errors[0].related()[0].1, "'b' is declared here without a value");
+    }
+}
@@ -0,0 +1,434 @@
+// errors reported by the parser and the semantic passes.
+//
+// a `ParseError` or `SemanticError` says what went wrong in terms of tokens and
+// spans, the message text only comes from its `Display` impl so the editor,
+// tests and command line tools all word errors the same way. the language
+// server turns errors into `types::diagnostics::Diagnostic`s right before
+// publishing them.
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::tokenizer::{Span, Token, TokenKind};
+use crate::typecheck::Type;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Severity {
+    Error = 1,
+    Warning = 2,
+    Information = 3,
+    Hint = 4,
+}
+
+// extra information on how the editor should show an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Tag {
+    // the code does nothing, editors fade it out
+    Unnecessary = 1,
+    Deprecated = 2,
+}
+
+// something the parser would have accepted where the error is
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Expected {
+    Token(TokenKind),
+    Identifier,
+    Type,
+    Expression,
+    Statement,
+    Pattern,
+    ImportPath,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ParseError {
+    // `context` names the construct being parsed, e.g. "if statement"
+    UnexpectedToken { found: TokenKind, expected: Vec<Expected>, context: &'static str, span: Span },
+    UnexpectedEof { expected: Vec<Expected>, context: &'static str, span: Span },
+    // `delimiter` is the closing token that is missing, `opening` the token it should close
+    UnclosedDelimiter { delimiter: TokenKind, opening: Span, found: TokenKind, span: Span },
+    UnknownKeyword { keyword: String, span: Span },
+    InvalidAssignmentTarget { span: Span },
+    // `previous` is the pattern of the earlier arm that covers this one
+    UnreachableMatchArm { previous: Span, span: Span },
+    // `const name;`, the span covers the name
+    ConstantWithoutInitializer { name: String, span: Span },
+    // an import inside a block, the span covers `import`
+    NestedImport { span: Span },
+    // an int literal above `i32::MAX`
+    IntegerTooLarge { span: Span },
+}
+
+impl ParseError {
+    // `found` where one of `expected` should have been, end of file gets its own error
+    pub fn unexpected(found: &Token, expected: Vec<Expected>, context: &'static str) -> ParseError {
+        match found.kind {
+            TokenKind::EOF => ParseError::UnexpectedEof { expected, context, span: found.span() },
+            _ => ParseError::UnexpectedToken { found: found.kind.clone(), expected, context, span: found.span() },
+        }
+    }
+
+    // stable identifier of the kind of error, stays the same when messages are reworded
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedToken { .. } => "E0001",
+            ParseError::UnexpectedEof { .. } => "E0002",
+            ParseError::UnclosedDelimiter { .. } => "E0003",
+            ParseError::UnknownKeyword { .. } => "E0004",
+            ParseError::InvalidAssignmentTarget { .. } => "E0005",
+            ParseError::UnreachableMatchArm { .. } => "W0001",
+            ParseError::ConstantWithoutInitializer { .. } => "E0006",
+            ParseError::NestedImport { .. } => "E0007",
+            ParseError::IntegerTooLarge { .. } => "E0008",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            ParseError::UnreachableMatchArm { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    // where the error is reported
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnexpectedEof { span, .. }
+            | ParseError::UnclosedDelimiter { span, .. }
+            | ParseError::UnknownKeyword { span, .. }
+            | ParseError::InvalidAssignmentTarget { span }
+            | ParseError::UnreachableMatchArm { span, .. }
+            | ParseError::ConstantWithoutInitializer { span, .. }
+            | ParseError::NestedImport { span }
+            | ParseError::IntegerTooLarge { span } => *span,
+        }
+    }
+
+    pub fn expected(&self) -> &[Expected] {
+        match self {
+            ParseError::UnexpectedToken { expected, .. } | ParseError::UnexpectedEof { expected, .. } => expected,
+            _ => &[],
+        }
+    }
+
+    // every span the error holds, for moving it along with the code it points at
+    pub fn spans_mut(&mut self) -> Vec<&mut Span> {
+        match self {
+            ParseError::UnclosedDelimiter { opening, span, .. } => vec![opening, span],
+            ParseError::UnreachableMatchArm { previous, span } => vec![previous, span],
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnexpectedEof { span, .. }
+            | ParseError::UnknownKeyword { span, .. }
+            | ParseError::InvalidAssignmentTarget { span }
+            | ParseError::ConstantWithoutInitializer { span, .. }
+            | ParseError::NestedImport { span }
+            | ParseError::IntegerTooLarge { span } => vec![span],
+        }
+    }
+
+    // other places the error refers to, each with a note on what is there
+    pub fn related(&self) -> Vec<(Span, String)> {
+        match self {
+            ParseError::UnreachableMatchArm { previous, .. } => vec![(*previous, String::from("covered by this arm"))],
+            _ => Vec::new(),
+        }
+    }
+}
+
+// how a token is referred to in messages
+pub fn describe(kind: &TokenKind) -> String {
+    let text = match kind {
+        TokenKind::SEMICOLON => ";",
+        TokenKind::COLON => ":",
+        TokenKind::DCOLON => "::",
+        TokenKind::LPAREN => "(",
+        TokenKind::RPAREN => ")",
+        TokenKind::LCURLY => "{",
+        TokenKind::RCURLY => "}",
+        TokenKind::LSQUARE => "[",
+        TokenKind::RSQUARE => "]",
+        TokenKind::COMMA => ",",
+        TokenKind::DOT => ".",
+        TokenKind::ADD => "+",
+        TokenKind::SUB => "-",
+        TokenKind::MUL => "*",
+        TokenKind::DIV => "/",
+        TokenKind::MOD => "%",
+        TokenKind::BANG => "!",
+        TokenKind::QUESTION => "?",
+        TokenKind::ASSIGNE => "=",
+        TokenKind::FATARROW => "=>",
+        TokenKind::EQ => "==",
+        TokenKind::GT => ">",
+        TokenKind::LT => "<",
+        TokenKind::GTEQ => ">=",
+        TokenKind::LTEQ => "<=",
+        TokenKind::NEQ => "!=",
+        TokenKind::INT(Some(value)) => return format!("number {}", value),
+        TokenKind::INT(None) => return String::from("a number too large for an int"),
+        TokenKind::FLOAT(value) => return format!("number {}", value),
+        TokenKind::STRING(value) => return format!("string \"{}\"", value),
+        TokenKind::IDENTIFIER(name) => return format!("identifier '{}'", name),
+        TokenKind::KEYWORD(keyword) => return format!("keyword '{}'", keyword),
+        TokenKind::EOF => return String::from("end of file"),
+    };
+    format!("'{}'", text)
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expected::Token(kind) => write!(f, "{}", describe(kind)),
+            Expected::Identifier => write!(f, "identifier"),
+            Expected::Type => write!(f, "a type"),
+            Expected::Expression => write!(f, "an expression"),
+            Expected::Statement => write!(f, "a statement"),
+            Expected::Pattern => write!(f, "a literal, '_' or an identifier"),
+            Expected::ImportPath => write!(f, "a path string"),
+        }
+    }
+}
+
+// `a`, `a or b`, `a, b or c`
+fn one_of(expected: &[Expected]) -> String {
+    let items: Vec<String> = expected.iter().map(|item| item.to_string()).collect();
+    match items.split_last() {
+        None => String::from("something else"),
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedToken { found, expected, context, .. } => {
+                write!(f, "invalid syntax for {}, expected {} found {}", context, one_of(expected), describe(found))
+            }
+            ParseError::UnexpectedEof { expected, context, .. } => {
+                write!(f, "unexpected end of file in {}, expected {}", context, one_of(expected))
+            }
+            ParseError::UnclosedDelimiter { delimiter, found, .. } => {
+                write!(f, "unclosed delimiter, expected {} found {}", describe(delimiter), describe(found))
+            }
+            ParseError::UnknownKeyword { keyword, .. } => write!(f, "unimplemented keyword '{}'", keyword),
+            ParseError::InvalidAssignmentTarget { .. } => {
+                write!(f, "invalid assignment target, only variables, index and member expressions can be assigned to")
+            }
+            ParseError::UnreachableMatchArm { .. } => write!(f, "unreachable match arm, already covered by an earlier arm"),
+            ParseError::ConstantWithoutInitializer { name, .. } => {
+                write!(f, "constant '{}' needs a value, expected '=' and an initializer", name)
+            }
+            ParseError::NestedImport { .. } => write!(f, "imports are only allowed at the top level of a file"),
+            ParseError::IntegerTooLarge { .. } => {
+                write!(f, "integer literal is too large, the largest int is {}", i32::MAX)
+            }
+        }
+    }
+}
+
+// problems found in a program that parsed, by `resolver` and the passes after it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum SemanticError {
+    UndefinedVariable { name: String, span: Span },
+    // `declaration` is the declaration further down that the name refers to
+    UseBeforeDeclaration { name: String, span: Span, declaration: Span },
+    // a second declaration of `name` in the scope that declared it at `previous`
+    Redeclaration { name: String, span: Span, previous: Span },
+    // a declaration hiding the one of an enclosing scope at `shadowed`, only reported when asked for
+    Shadowing { name: String, span: Span, shadowed: Span },
+    // the span covers the assignment, `declaration` is the name in the const declaration
+    AssignmentToConstant { name: String, span: Span, declaration: Span },
+    // a variable or match binding whose value is never read
+    UnusedVariable { name: String, span: Span },
+    // an expression statement whose value is thrown away without any effect
+    UnusedResult { span: Span },
+    TypeMismatch { expected: Type, found: Type, span: Span },
+    // the span covers the whole binary expression
+    InvalidOperands { operator: TokenKind, left: Type, right: Type, span: Span },
+    // the span covers the initializer
+    AnnotationMismatch { name: String, annotation: Type, found: Type, span: Span },
+    UnknownType { name: String, span: Span },
+    NoField { ty: Type, field: String, span: Span },
+    NotIndexable { found: Type, span: Span },
+    // `function` is the callee as written, `signature` the signature of the function it names. the
+    // span covers the arguments that are too many, or the whole call when some are missing
+    ArgumentCount { function: String, expected: usize, found: usize, span: Span, signature: Option<Span> },
+    // the span covers the argument
+    ArgumentMismatch { function: String, expected: Type, found: Type, span: Span, signature: Option<Span> },
+    // the span covers the callee
+    NotCallable { found: Type, span: Span },
+    // found while folding constants, the span covers the whole operation
+    DivisionByZero { span: Span },
+    IntegerOverflow { span: Span },
+    // the span covers every statement that is never reached
+    UnreachableCode { span: Span },
+    // an `if` or `while` condition that is always `value`
+    ConstantCondition { value: bool, span: Span },
+    JumpOutsideLoop { keyword: &'static str, span: Span },
+    // a function with a return type that can end without a `return`, the span covers the return type
+    MissingReturn { name: String, returns: String, span: Span },
+    // a read of a variable that isn't assigned on some path to it, `declaration` is its name
+    PossiblyUninitialized { name: String, span: Span, declaration: Span },
+}
+
+impl SemanticError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SemanticError::UndefinedVariable { .. } => "E0201",
+            SemanticError::UseBeforeDeclaration { .. } => "E0202",
+            SemanticError::Redeclaration { .. } => "E0203",
+            SemanticError::Shadowing { .. } => "W0203",
+            SemanticError::AssignmentToConstant { .. } => "E0204",
+            SemanticError::UnusedVariable { .. } => "W0201",
+            SemanticError::UnusedResult { .. } => "W0202",
+            SemanticError::TypeMismatch { .. } => "E0301",
+            SemanticError::InvalidOperands { .. } => "E0302",
+            SemanticError::AnnotationMismatch { .. } => "E0303",
+            SemanticError::UnknownType { .. } => "E0304",
+            SemanticError::NoField { .. } => "E0305",
+            SemanticError::NotIndexable { .. } => "E0306",
+            SemanticError::ArgumentCount { .. } => "E0307",
+            SemanticError::ArgumentMismatch { .. } => "E0308",
+            SemanticError::NotCallable { .. } => "E0309",
+            SemanticError::DivisionByZero { .. } => "E0401",
+            SemanticError::IntegerOverflow { .. } => "E0402",
+            SemanticError::UnreachableCode { .. } => "W0501",
+            SemanticError::ConstantCondition { .. } => "W0502",
+            SemanticError::JumpOutsideLoop { .. } => "E0501",
+            SemanticError::MissingReturn { .. } => "E0502",
+            SemanticError::PossiblyUninitialized { .. } => "E0601",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            SemanticError::UnusedVariable { .. }
+            | SemanticError::UnusedResult { .. }
+            | SemanticError::Shadowing { .. }
+            | SemanticError::UnreachableCode { .. }
+            | SemanticError::ConstantCondition { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    pub fn tags(&self) -> Vec<Tag> {
+        match self {
+            SemanticError::UnusedVariable { .. }
+            | SemanticError::UnusedResult { .. }
+            | SemanticError::UnreachableCode { .. }
+            | SemanticError::ConstantCondition { .. } => vec![Tag::Unnecessary],
+            _ => Vec::new(),
+        }
+    }
+
+    // other places that explain the error, with what each of them is
+    pub fn related(&self) -> Vec<(Span, String)> {
+        match self {
+            SemanticError::UseBeforeDeclaration { name, declaration, .. } => {
+                vec![(*declaration, format!("'{}' is declared here", name))]
+            }
+            SemanticError::Redeclaration { name, previous, .. } => {
+                vec![(*previous, format!("'{}' is first declared here", name))]
+            }
+            SemanticError::Shadowing { name, shadowed, .. } => {
+                vec![(*shadowed, format!("the shadowed '{}' is declared here", name))]
+            }
+            SemanticError::AssignmentToConstant { name, declaration, .. } => {
+                vec![(*declaration, format!("'{}' is declared as a constant here", name))]
+            }
+            SemanticError::ArgumentCount { function, signature: Some(signature), .. }
+            | SemanticError::ArgumentMismatch { function, signature: Some(signature), .. } => {
+                vec![(*signature, format!("'{}' is declared here", function))]
+            }
+            SemanticError::PossiblyUninitialized { name, declaration, .. } => {
+                vec![(*declaration, format!("'{}' is declared here without a value", name))]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            SemanticError::UndefinedVariable { span, .. }
+            | SemanticError::UseBeforeDeclaration { span, .. }
+            | SemanticError::Redeclaration { span, .. }
+            | SemanticError::Shadowing { span, .. }
+            | SemanticError::AssignmentToConstant { span, .. }
+            | SemanticError::UnusedVariable { span, .. }
+            | SemanticError::UnusedResult { span }
+            | SemanticError::TypeMismatch { span, .. }
+            | SemanticError::InvalidOperands { span, .. }
+            | SemanticError::AnnotationMismatch { span, .. }
+            | SemanticError::UnknownType { span, .. }
+            | SemanticError::NoField { span, .. }
+            | SemanticError::NotIndexable { span, .. }
+            | SemanticError::ArgumentCount { span, .. }
+            | SemanticError::ArgumentMismatch { span, .. }
+            | SemanticError::NotCallable { span, .. }
+            | SemanticError::DivisionByZero { span }
+            | SemanticError::IntegerOverflow { span }
+            | SemanticError::UnreachableCode { span }
+            | SemanticError::ConstantCondition { span, .. }
+            | SemanticError::JumpOutsideLoop { span, .. }
+            | SemanticError::MissingReturn { span, .. }
+            | SemanticError::PossiblyUninitialized { span, .. } => *span,
+        }
+    }
+}
+
+impl fmt::Display for SemanticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SemanticError::UndefinedVariable { name, .. } => write!(f, "undefined variable '{}'", name),
+            SemanticError::UseBeforeDeclaration { name, declaration, .. } => {
+                write!(f, "'{}' is used before its declaration on line {}", name, declaration.start_line)
+            }
+            SemanticError::Redeclaration { name, .. } => write!(f, "'{}' is already declared in this scope", name),
+            SemanticError::Shadowing { name, shadowed, .. } => {
+                write!(f, "'{}' shadows the variable declared on line {}", name, shadowed.start_line)
+            }
+            SemanticError::AssignmentToConstant { name, .. } => write!(f, "cannot assign twice to constant '{}'", name),
+            SemanticError::UnusedVariable { name, .. } => {
+                write!(f, "unused variable '{}', prefix it with an underscore if this is intended", name)
+            }
+            SemanticError::UnusedResult { .. } => write!(f, "unused result, the value of this expression is never used"),
+            SemanticError::TypeMismatch { expected, found, .. } => {
+                write!(f, "mismatched types, expected {} found {}", expected, found)
+            }
+            SemanticError::InvalidOperands { operator, left, right, .. } => {
+                write!(f, "operator {} can't be applied to {} and {}", describe(operator), left, right)
+            }
+            SemanticError::AnnotationMismatch { name, annotation, found, .. } => {
+                write!(f, "'{}' is declared as {} but its initializer is {}", name, annotation, found)
+            }
+            SemanticError::UnknownType { name, .. } => write!(f, "unknown type '{}'", name),
+            SemanticError::NoField { ty, field, .. } => write!(f, "type {} has no field '{}'", ty, field),
+            SemanticError::NotIndexable { found, .. } => write!(f, "{} can't be indexed, only arrays can", found),
+            SemanticError::ArgumentCount { function, expected, found, .. } => {
+                let plural = if *expected == 1 { "" } else { "s" };
+                let verb = if *found == 1 { "was" } else { "were" };
+                write!(f, "'{}' takes {} argument{} but {} {} given", function, expected, plural, found, verb)
+            }
+            SemanticError::ArgumentMismatch { function, expected, found, .. } => {
+                write!(f, "mismatched argument to '{}', expected {} found {}", function, expected, found)
+            }
+            SemanticError::NotCallable { found, .. } => write!(f, "{} can't be called, only functions can", found),
+            SemanticError::DivisionByZero { .. } => write!(f, "division by zero"),
+            SemanticError::IntegerOverflow { .. } => {
+                write!(f, "integer overflow, the result doesn't fit in an int")
+            }
+            SemanticError::UnreachableCode { .. } => write!(f, "unreachable code"),
+            SemanticError::ConstantCondition { value, .. } => write!(f, "this condition is always {}", value),
+            SemanticError::JumpOutsideLoop { keyword, .. } => write!(f, "'{}' outside of a loop", keyword),
+            SemanticError::MissingReturn { name, returns, .. } => {
+                write!(f, "'{}' returns {} but can end without returning a value", name, returns)
+            }
+            SemanticError::PossiblyUninitialized { name, .. } => {
+                write!(f, "'{}' is used before it is assigned on every path", name)
+            }
+        }
+    }
+}
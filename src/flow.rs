@@ -0,0 +1,211 @@
+// reachability.
+//
+// `check_flow` reports statements that can never run and conditions whose value
+// is known before the program runs, both as warnings tagged `Unnecessary`. a
+// statement is unreachable when one before it in the same block always jumps
+// away: a `return`, `break` or `continue`, a block or `if` that always does or
+// a loop that never ends. conditions are evaluated by `fold`, so `if (false)`,
+// `if (1 > 2)` and conditions built from constants are all recognised, except
+// for `while (true)` which is how an endless loop is written. only bools are
+// conditions, a number in their place is left to the type checker. `break` and
+// `continue` outside of a loop are errors, and so is a function with a return
+// type whose body can end without a `return`.
+
+use crate::arena::{Ast, ExprId, ExprNode, PatternNode, StmtId, StmtNode};
+use crate::error::SemanticError;
+use crate::fold::{Folding, Value};
+
+pub fn check_flow(ast: &Ast, folding: &Folding) -> Vec<SemanticError> {
+    let mut flow = Flow { folding, loops: Vec::new(), dead: false, errors: Vec::new() };
+    flow.statements(ast, ast.program());
+    flow.errors
+}
+
+// the expression inside any parentheses
+fn strip(ast: &Ast, expr: ExprId) -> &ExprNode {
+    match ast.expr(expr) {
+        ExprNode::PARENTHESIZED(inner) => strip(ast, *inner),
+        node => node,
+    }
+}
+
+struct Flow<'a> {
+    folding: &'a Folding,
+    // one entry per loop around the current statement, whether a reachable `break` leaves it
+    loops: Vec<bool>,
+    // inside code that was already reported as unreachable, nothing in it is reported again
+    dead: bool,
+    errors: Vec<SemanticError>,
+}
+
+impl Flow<'_> {
+    fn warn(&mut self, warning: SemanticError) {
+        if !self.dead {
+            self.errors.push(warning);
+        }
+    }
+
+    fn condition(&mut self, ast: &Ast, condition: ExprId) -> Option<bool> {
+        let Some(&Value::Bool(value)) = self.folding.value(condition) else { return None };
+        if let Some(span) = ast.span(condition) {
+            self.warn(SemanticError::ConstantCondition { value, span });
+        }
+        Some(value)
+    }
+
+    // checks statements that are never reached, reporting them once as a whole
+    fn unreachable(&mut self, ast: &Ast, statements: &[StmtId]) {
+        let spans: Vec<_> = statements.iter().filter_map(|stmt| ast.stmt_span(*stmt)).collect();
+        if let (Some(first), Some(last)) = (spans.first(), spans.last()) {
+            self.warn(SemanticError::UnreachableCode { span: first.to(*last) });
+        }
+
+        let dead = std::mem::replace(&mut self.dead, true);
+        for stmt in statements {
+            self.stmt(ast, *stmt);
+        }
+        self.dead = dead;
+    }
+
+    // checks a list of statements, true if running them always jumps away
+    fn statements(&mut self, ast: &Ast, statements: &[StmtId]) -> bool {
+        for (index, stmt) in statements.iter().enumerate() {
+            if self.stmt(ast, *stmt) {
+                let rest: Vec<StmtId> = statements[index + 1..]
+                    .iter()
+                    .copied()
+                    .filter(|stmt| !matches!(ast.stmt(*stmt), StmtNode::EOF))
+                    .collect();
+                self.unreachable(ast, &rest);
+                return true;
+            }
+        }
+        false
+    }
+
+    // checks a statement, true if control never goes on to the statement after it
+    fn stmt(&mut self, ast: &Ast, stmt: StmtId) -> bool {
+        match *ast.stmt(stmt) {
+            StmtNode::RETURN(_, _) => true,
+            StmtNode::BREAK(span) | StmtNode::CONTINUE(span) => {
+                let is_break = matches!(ast.stmt(stmt), StmtNode::BREAK(_));
+                match self.loops.last_mut() {
+                    Some(breaks) => *breaks |= is_break && !self.dead,
+                    None => {
+                        let keyword = if is_break { "break" } else { "continue" };
+                        self.errors.push(SemanticError::JumpOutsideLoop { keyword, span });
+                    }
+                }
+                true
+            }
+            StmtNode::Block(statements) => self.statements(ast, ast.stmt_list(statements)),
+            StmtNode::FunctionDeclaration(function) => {
+                // a loop around the function doesn't make `break` valid in its body
+                let loops = std::mem::take(&mut self.loops);
+                let returns = self.stmt(ast, function.body);
+                self.loops = loops;
+                let return_type = function.return_type.filter(|return_type| ast.name(return_type.name) != "void");
+                if let (Some(return_type), false) = (return_type, returns) {
+                    self.errors.push(SemanticError::MissingReturn {
+                        name: ast.name(function.name).to_string(),
+                        returns: ast.name(return_type.name).to_string(),
+                        span: return_type.span,
+                    });
+                }
+                false
+            }
+            StmtNode::IFSTATEMENT(condition, body) => match self.condition(ast, condition) {
+                Some(false) => {
+                    self.unreachable(ast, &[body]);
+                    false
+                }
+                Some(true) => self.stmt(ast, body),
+                None => {
+                    self.stmt(ast, body);
+                    false
+                }
+            },
+            StmtNode::WHILE(condition, body) => {
+                let endless = matches!(strip(ast, condition), ExprNode::BOOLLITERAL(true, _));
+                let known = if endless { Some(true) } else { self.condition(ast, condition) };
+                if known == Some(false) {
+                    self.unreachable(ast, &[body]);
+                    return false;
+                }
+
+                self.loops.push(false);
+                self.stmt(ast, body);
+                let breaks = self.loops.pop().unwrap();
+                // a loop that always goes round again is only left through a `break`
+                known == Some(true) && !breaks
+            }
+            StmtNode::MATCH(_, arms) => {
+                let arms = ast.match_arms(arms);
+                let mut every_arm = true;
+                for arm in arms {
+                    every_arm &= self.stmt(ast, arm.body);
+                }
+                let exhaustive = arms.iter().any(|arm| !matches!(arm.pattern, PatternNode::Literal(_, _)));
+                every_arm && exhaustive
+            }
+            StmtNode::EOF
+            | StmtNode::VariableDeclaration(_)
+            | StmtNode::StructDeclaration(_, _)
+            | StmtNode::IMPORT(_, _)
+            | StmtNode::EXPR(_, _) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Severity, Tag};
+    use crate::fold::fold_constants;
+    use crate::tests::{reported, resolved, Resolved};
+    use crate::typecheck::check_types;
+
+    #[test]
+    fn test_unreachable_code_and_constant_conditions_are_reported() {
+        let source = "const LIMIT = 10;\nlet i = 0;\nwhile (true) {\n    if (i > LIMIT) {\n        break;\n        i = 0;\n    }\n    i = i + 1;\n    continue;\n    i = 2;\n}\nif (false) {\n    i = 3;\n}\nif (1 > 2) { i = 4; }\nwhile (LIMIT > 5) {\n    i = 5; break;\n}\nbreak;\nreturn i;\ni = 6;\ni = 7;";
+        let Resolved { ast, resolution, .. } = resolved(source);
+        let errors = check_flow(&ast, &fold_constants(&ast, &resolution));
+
+        assert_eq!(
+            reported(&errors),
+            [
+                "6: W0501 unreachable code",
+                "10: W0501 unreachable code",
+                "12: W0502 this condition is always false",
+                "13: W0501 unreachable code",
+                "15: W0502 this condition is always false",
+                "15: W0501 unreachable code",
+                "16: W0502 this condition is always true",
+                "19: E0501 'break' outside of a loop",
+                "20: W0501 unreachable code",
+            ]
+        );
+        // everything after the top level `break` is one warning
+        assert!(matches!(errors[8], SemanticError::UnreachableCode { span } if span.end_line == 22));
+        assert!(errors
+            .iter()
+            .filter(|error| error.severity() == Severity::Warning)
+            .all(|error| error.tags() == [Tag::Unnecessary]));
+    }
+
+    #[test]
+    fn test_numeric_conditions_are_type_errors_not_constant_conditions() {
+        let Resolved { tree, ast, resolution } = resolved("let i = 0;\nif (0) {\n    i = 1;\n}\nwhile (1.5) { i = 2; }\nlet j = 0 ? 1 : 2;");
+        assert_eq!(
+            reported(&check_types(&tree.program, &resolution).errors),
+            [
+                "2: E0301 mismatched types, expected bool found int",
+                "5: E0301 mismatched types, expected bool found float",
+                "6: E0301 mismatched types, expected bool found int",
+            ]
+        );
+
+        // the checker only takes bools, so a number isn't a condition that is known to be true or false
+        assert!(check_flow(&ast, &fold_constants(&ast, &resolution)).is_empty());
+    }
+}
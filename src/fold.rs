@@ -0,0 +1,339 @@
+// constant folding.
+//
+// `fold_constants` works out the value of every expression that only depends on
+// literals and `const` bindings. it runs over the arena form of the program so
+// the values can be looked up by `ExprId`. the value of a `const` initializer is
+// carried to every use of the constant, `let` bindings can be reassigned and
+// are never folded. integer arithmetic that overflows and integer division by
+// zero are reported, the expression then has no value so the error isn't
+// repeated by the expressions around it. floats follow IEEE 754, dividing one by
+// zero gives an infinity or NaN and isn't an error. of a conditional with a
+// constant condition only the branch that is taken is folded, nothing in the
+// other one can fail.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::arena::{self, Ast, BinaryOp, ExprId, ExprNode, StmtId, StmtNode};
+use crate::error::SemanticError;
+use crate::resolver::{DeclarationId, Resolution};
+use crate::tokenizer::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Float(f32),
+    String(String),
+    Bool(bool),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::String(value) => write!(f, "\"{}\"", value),
+            Value::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Folding {
+    values: HashMap<ExprId, Value>,
+    // the value of every constant with a constant initializer
+    pub constants: HashMap<DeclarationId, Value>,
+    pub errors: Vec<SemanticError>,
+}
+
+impl Folding {
+    // the value of `expr`, `None` unless it is known before the program runs
+    pub fn value(&self, expr: ExprId) -> Option<&Value> {
+        self.values.get(&expr)
+    }
+}
+
+pub fn fold_constants(ast: &Ast, resolution: &Resolution) -> Folding {
+    let mut folder = Folder {
+        ast,
+        resolution,
+        declarations: resolution
+            .declarations
+            .iter()
+            .enumerate()
+            .map(|(index, declaration)| (declaration.span, DeclarationId(index)))
+            .collect(),
+        folding: Folding::default(),
+    };
+    arena::Visitor::visit_program(&mut folder, ast.program());
+    folder.folding
+}
+
+// why an operation has no value
+enum Failure {
+    DivisionByZero,
+    Overflow,
+    // the operands don't fit the operator, the type checker reports that
+    NotConstant,
+}
+
+struct Folder<'a> {
+    ast: &'a Ast,
+    resolution: &'a Resolution,
+    declarations: HashMap<Span, DeclarationId>,
+    folding: Folding,
+}
+
+impl Folder<'_> {
+    // folds `expr` and everything below it, recording every value found on the way
+    fn fold(&mut self, expr: ExprId) -> Option<Value> {
+        let ast = self.ast;
+        let value = match *ast.expr(expr) {
+            ExprNode::INTEGERLITERAL(value, _) => Some(Value::Int(value)),
+            ExprNode::FLOATLITERAL(value, _) => Some(Value::Float(value)),
+            ExprNode::STRINGLITERAL(value, _) => Some(Value::String(ast.name(value).to_string())),
+            ExprNode::BOOLLITERAL(value, _) => Some(Value::Bool(value)),
+            ExprNode::IDENTIFIER(_, span) => {
+                let declaration = self.resolution.references.get(&span);
+                declaration.and_then(|declaration| self.folding.constants.get(declaration)).cloned()
+            }
+            ExprNode::PARENTHESIZED(inner) => self.fold(inner),
+            ExprNode::UNARY(operand) => match self.fold(operand) {
+                Some(Value::Bool(value)) => Some(Value::Bool(!value)),
+                _ => None,
+            },
+            ExprNode::BINARYEXPR(left, right, operator, operator_span) => {
+                let left = self.fold(left);
+                let right = self.fold(right);
+                let (Some(left), Some(right)) = (left, right) else { return None };
+                match binary(operator, left, right) {
+                    Ok(value) => Some(value),
+                    Err(failure) => {
+                        let span = ast.span(expr).unwrap_or(operator_span);
+                        match failure {
+                            Failure::DivisionByZero => self.folding.errors.push(SemanticError::DivisionByZero { span }),
+                            Failure::Overflow => self.folding.errors.push(SemanticError::IntegerOverflow { span }),
+                            Failure::NotConstant => {}
+                        }
+                        None
+                    }
+                }
+            }
+            ExprNode::CONDITIONAL(condition, then, otherwise) => match self.fold(condition) {
+                Some(Value::Bool(true)) => self.fold(then),
+                Some(Value::Bool(false)) => self.fold(otherwise),
+                _ => {
+                    self.fold(then);
+                    self.fold(otherwise);
+                    None
+                }
+            },
+            ExprNode::INDEX(left, right) | ExprNode::ASSIGNMENT(left, right) => {
+                self.fold(left);
+                self.fold(right);
+                None
+            }
+            ExprNode::MEMBER(inner, _) => {
+                self.fold(inner);
+                None
+            }
+            ExprNode::ARRAY(elements) => {
+                for element in ast.expr_list(elements) {
+                    self.fold(*element);
+                }
+                None
+            }
+            ExprNode::CALL(callee, arguments) => {
+                self.fold(callee);
+                for argument in ast.expr_list(arguments) {
+                    self.fold(*argument);
+                }
+                None
+            }
+            ExprNode::STRUCTLITERAL(_, fields) => {
+                for field in ast.field_initializers(fields) {
+                    self.fold(field.value);
+                }
+                None
+            }
+            ExprNode::PATH(_) | ExprNode::Error => None,
+        };
+
+        if let Some(value) = &value {
+            self.folding.values.insert(expr, value.clone());
+        }
+        value
+    }
+}
+
+fn binary(operator: BinaryOp, left: Value, right: Value) -> Result<Value, Failure> {
+    match (left, right) {
+        (Value::Int(left), Value::Int(right)) => integer(operator, left, right),
+        (Value::Int(left), Value::Float(right)) => float(operator, left as f32, right),
+        (Value::Float(left), Value::Int(right)) => float(operator, left, right as f32),
+        (Value::Float(left), Value::Float(right)) => float(operator, left, right),
+        (Value::String(left), Value::String(right)) => match operator {
+            BinaryOp::Add => Ok(Value::String(left + &right)),
+            BinaryOp::Eq => Ok(Value::Bool(left == right)),
+            BinaryOp::Neq => Ok(Value::Bool(left != right)),
+            _ => Err(Failure::NotConstant),
+        },
+        (Value::Bool(left), Value::Bool(right)) => match operator {
+            BinaryOp::Eq => Ok(Value::Bool(left == right)),
+            BinaryOp::Neq => Ok(Value::Bool(left != right)),
+            _ => Err(Failure::NotConstant),
+        },
+        _ => Err(Failure::NotConstant),
+    }
+}
+
+fn integer(operator: BinaryOp, left: i32, right: i32) -> Result<Value, Failure> {
+    if matches!(operator, BinaryOp::Div | BinaryOp::Mod) && right == 0 {
+        return Err(Failure::DivisionByZero);
+    }
+    let result = match operator {
+        BinaryOp::Add => left.checked_add(right),
+        BinaryOp::Sub => left.checked_sub(right),
+        BinaryOp::Mul => left.checked_mul(right),
+        BinaryOp::Div => left.checked_div(right),
+        BinaryOp::Mod => left.checked_rem(right),
+        _ => return Ok(Value::Bool(compare(operator, left, right))),
+    };
+    result.map(Value::Int).ok_or(Failure::Overflow)
+}
+
+fn float(operator: BinaryOp, left: f32, right: f32) -> Result<Value, Failure> {
+    let result = match operator {
+        BinaryOp::Add => left + right,
+        BinaryOp::Sub => left - right,
+        BinaryOp::Mul => left * right,
+        BinaryOp::Div => left / right,
+        BinaryOp::Mod => left % right,
+        _ => return Ok(Value::Bool(compare(operator, left, right))),
+    };
+    Ok(Value::Float(result))
+}
+
+fn compare<T: PartialOrd>(operator: BinaryOp, left: T, right: T) -> bool {
+    match operator {
+        BinaryOp::Eq => left == right,
+        BinaryOp::Neq => left != right,
+        BinaryOp::Lt => left < right,
+        BinaryOp::LtEq => left <= right,
+        BinaryOp::Gt => left > right,
+        BinaryOp::GtEq => left >= right,
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => {
+            unreachable!("arithmetic is handled by the caller")
+        }
+    }
+}
+
+impl<'a> arena::Visitor<'a> for Folder<'a> {
+    fn ast(&self) -> &'a Ast {
+        self.ast
+    }
+
+    fn visit_stmt(&mut self, stmt: StmtId) {
+        if let StmtNode::VariableDeclaration(declaration) = *self.ast.stmt(stmt) {
+            let value = declaration.initializer.and_then(|initializer| self.fold(initializer));
+            let id = self.declarations.get(&declaration.span).copied();
+            if let (true, Some(id), Some(value)) = (declaration.constant, id, value) {
+                self.folding.constants.insert(id, value);
+            }
+            return;
+        }
+        arena::walk_stmt(self, stmt);
+    }
+
+    // `fold` already goes through the children
+    fn visit_expr(&mut self, expr: ExprId) {
+        self.fold(expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ParseError;
+    use crate::resolver::resolve;
+    use crate::tests::{reported, resolved, Resolved};
+
+    // the folded value of the initializer of each of `stmts`, which are all declarations
+    fn initializers<'a>(ast: &Ast, folding: &'a Folding, stmts: &[StmtId]) -> Vec<Option<&'a Value>> {
+        stmts
+            .iter()
+            .map(|stmt| match ast.stmt(*stmt) {
+                StmtNode::VariableDeclaration(declaration) => folding.value(declaration.initializer.unwrap()),
+                other => panic!("expected a declaration, got {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_constant_folding_propagates_constants_and_reports_errors() {
+        let source = "const PI = 3.5;\nconst R = 2;\nlet area = R * R * PI;\nlet r = 5;\nlet circle = r * r * PI;\nconst BIG = R > 1 ? \"big\" + \"!\" : \"small\";\nlet overflow = 2147483647 + R;\nlet zero = 10 / (R - 2);\nlet later = zero * 2;";
+        let Resolved { ast, resolution, .. } = resolved(source);
+        let folding = fold_constants(&ast, &resolution);
+
+        assert_eq!(
+            initializers(&ast, &folding, ast.program()),
+            [
+                Some(&Value::Float(3.5)),
+                Some(&Value::Int(2)),
+                Some(&Value::Float(14.0)),
+                Some(&Value::Int(5)),
+                // `r` can be reassigned, only the constant part is folded
+                None,
+                Some(&Value::String("big!".to_string())),
+                None,
+                None,
+                None,
+            ]
+        );
+        assert_eq!(folding.constants.len(), 3);
+
+        assert_eq!(
+            reported(&folding.errors),
+            ["7: E0402 integer overflow, the result doesn't fit in an int", "8: E0401 division by zero"]
+        );
+        let columns: Vec<u32> = folding.errors.iter().map(|error| error.span().start_column).collect();
+        assert_eq!(columns, [16, 12]);
+    }
+
+    #[test]
+    fn test_folding_skips_dead_branches_and_divides_floats_by_zero() {
+        let source = "const ON = true;\nlet a = ON ? 1 : 1 / 0;\nlet b = !ON ? 1 / 0 : 2;\nlet c = x ? 1 : 1 / 0;\nlet d = 1.0 / 0.0;\nlet e = 0.0 / 0;\nlet f = 1 % 0;";
+        let Resolved { ast, resolution, .. } = resolved(source);
+        let folding = fold_constants(&ast, &resolution);
+
+        let values = initializers(&ast, &folding, &ast.program()[1..]);
+        assert_eq!(&values[..4], [Some(&Value::Int(1)), Some(&Value::Int(2)), None, Some(&Value::Float(f32::INFINITY))]);
+        assert!(matches!(values[4], Some(Value::Float(value)) if value.is_nan()));
+
+        // the branch a constant condition rules out is never reported, one that may run still is
+        assert_eq!(reported(&folding.errors), ["4: E0401 division by zero", "7: E0401 division by zero"]);
+    }
+
+    #[test]
+    fn test_int_literals_too_large_are_reported_instead_of_folded() {
+        let source = "let a = 2147483648 + 1;\nlet b = 2147483647;\nmatch (b) { 99999999999 => a, 99999999999 => b }";
+        let tree = crate::syntax::parse(source);
+        let spans: Vec<(u32, u32, u32)> = tree
+            .errors
+            .iter()
+            .map(|error| match error {
+                ParseError::IntegerTooLarge { span } => (span.start_line, span.start_column, span.end_column),
+                other => panic!("expected a literal that is too large, got {:?}", other),
+            })
+            .collect();
+        // the second arm isn't unreachable, neither literal has a value to compare
+        assert_eq!(spans, [(1, 9, 18), (3, 13, 23), (3, 31, 41)]);
+        assert_eq!(tree.errors[0].code(), "E0008");
+        assert_eq!(tree.errors[0].to_string(), "integer literal is too large, the largest int is 2147483647");
+
+        let ast = Ast::from_program(&tree.program);
+        let folding = fold_constants(&ast, &resolve(&tree.program));
+        assert_eq!(initializers(&ast, &folding, &ast.program()[..2]), [None, Some(&Value::Int(i32::MAX))]);
+        assert!(folding.errors.is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+// incremental reparsing of documents that are edited in the editor.
+//
+// `Parser::parse` splits a file into chunks, one per iteration of its top level
+// loop, and records the furthest token each chunk looked at. after an edit:
+//
+// - chunks that never looked at the edited text are kept as they are
+// - chunks that start on a line after the edit are kept with their line numbers
+//   shifted, as soon as the reparsed text in front of them ends cleanly
+// - only the text in between is tokenized and parsed again
+//
+// the tree, AST and errors end up the same as the ones of a full parse.
+
+use std::{ops::Range as ByteRange, rc::Rc};
+
+use crate::ast;
+use crate::error::ParseError;
+use crate::parser::{Parser, Stmt};
+use crate::syntax::{build_tree, token_ranges, GreenElement, GreenNode, SyntaxKind, SyntaxNode, SyntaxTree};
+use crate::tokenizer::{Span, TokenKind, Tokenizer};
+use crate::types::{Position, Range};
+use crate::visit::VisitorMut;
+
+// chunks after the edit tried as the end of the reparsed text, past that the
+// rest of the file is reparsed
+const MAX_SYNC_ATTEMPTS: usize = 4;
+
+// a `parser::Chunk` in byte offsets
+#[derive(Debug, Clone, PartialEq)]
+struct Chunk {
+    // from the start of the first token to the end of the last one
+    range: ByteRange<usize>,
+    // end of the furthest token looked at while parsing the chunk
+    lookahead: usize,
+    statements: usize,
+    errors: usize,
+    nodes: usize,
+}
+
+// a source file kept in sync with the edits made to it
+pub struct Document {
+    source: String,
+    tree: SyntaxTree,
+    chunks: Vec<Chunk>,
+}
+
+// the output of parsing a piece of text on its own
+struct Parsed {
+    root: Rc<GreenNode>,
+    program: Vec<Stmt>,
+    errors: Vec<ParseError>,
+    chunks: Vec<Chunk>,
+    // something was decided by looking at the end of the text, with more text
+    // following it could have been parsed differently
+    open_ended: bool,
+}
+
+fn parse_text(source: &str) -> Parsed {
+    let tokens = Tokenizer::tokenize(source);
+    let mut parser = Parser::new(&tokens);
+    parser.parse();
+    let root = Rc::new(build_tree(source, &tokens, parser.events()));
+    let program = ast::lower(source, &tokens, &SyntaxNode::new_root(root.clone()));
+
+    let ranges = token_ranges(source, &tokens);
+    let eof = tokens.len() - 1;
+    let chunks = parser
+        .chunks
+        .iter()
+        .map(|chunk| Chunk {
+            range: ranges[chunk.tokens.start].start..ranges[chunk.tokens.end - 1].end,
+            lookahead: ranges[chunk.lookahead].end,
+            statements: chunk.statements.len(),
+            errors: chunk.errors.len(),
+            nodes: chunk.nodes,
+        })
+        .collect();
+
+    // a chunk that looked at EOF, or a string left open up to the end of the text
+    let open_ended = parser.chunks.iter().any(|chunk| chunk.lookahead >= eof)
+        || tokens.iter().zip(&ranges).any(|(token, range)| {
+            !matches!(token.kind, TokenKind::EOF) && range.end == source.len()
+        });
+
+    Parsed {
+        root,
+        program,
+        errors: std::mem::take(&mut parser.errors),
+        chunks,
+        open_ended,
+    }
+}
+
+impl Document {
+    pub fn new(source: &str) -> Document {
+        let parsed = parse_text(source);
+        Document {
+            source: source.to_string(),
+            tree: SyntaxTree {
+                root: SyntaxNode::new_root(parsed.root),
+                program: parsed.program,
+                errors: parsed.errors,
+            },
+            chunks: parsed.chunks,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn tree(&self) -> &SyntaxTree {
+        &self.tree
+    }
+
+    // replaces the text inside `range` with `text` and reparses what the edit
+    // could have changed. positions are the editor's: 0 based lines and columns
+    pub fn edit(&mut self, range: Range, text: &str) {
+        let start = self.offset(range.start);
+        let end = self.offset(range.end).max(start);
+        let source = format!("{}{}{}", &self.source[..start], text, &self.source[end..]);
+        let delta = text.len() as isize - (end - start) as isize;
+        let lines = text.matches('\n').count() as i64 - self.source[start..end].matches('\n').count() as i64;
+
+        // chunks that never looked at the edited text parse the same way
+        let prefix = self.chunks.iter().take_while(|chunk| chunk.lookahead < start).count();
+        let old_children = &self.tree.root.green().children;
+        let prefix_children = match prefix_nodes(&self.chunks[..prefix]) {
+            0 => 0,
+            nodes => node_index(old_children, nodes - 1) + 1,
+        };
+        let from: usize = old_children[..prefix_children].iter().map(|child| child.width()).sum();
+
+        // reparse up to a chunk starting on a line after the edit, the reparsed
+        // text has to end cleanly in front of it for the chunk to be kept
+        let candidates = (prefix..self.chunks.len())
+            .filter(|index| match line_start(&self.source, self.chunks[*index].range.start) {
+                Some(line_start) => line_start > end,
+                None => false,
+            })
+            .take(MAX_SYNC_ATTEMPTS);
+        let mut reparsed = None;
+        for candidate in candidates {
+            let sync = (self.chunks[candidate].range.start as isize + delta) as usize;
+            let middle = parse_text(&source[from..sync]);
+            if !middle.open_ended {
+                reparsed = Some((middle, candidate));
+                break;
+            }
+        }
+        let (mut middle, suffix) = match reparsed {
+            Some(reparsed) => reparsed,
+            None => (parse_text(&source[from..]), self.chunks.len()),
+        };
+
+        // the reparsed text was parsed as if it started the file
+        let before = &source[..from];
+        let mut shift = Shift {
+            line: 1,
+            columns: (from - before.rfind('\n').map(|newline| newline + 1).unwrap_or(0)) as u32,
+            lines: before.matches('\n').count() as i64,
+        };
+        shift.visit_program_mut(&mut middle.program);
+        for error in middle.errors.iter_mut() {
+            shift.error(error);
+        }
+        for chunk in middle.chunks.iter_mut() {
+            chunk.range = chunk.range.start + from..chunk.range.end + from;
+            chunk.lookahead += from;
+        }
+
+        // chunks after it only moved by whole lines
+        let mut shift = Shift { line: 0, columns: 0, lines };
+        let mut suffix_chunks = self.chunks.split_off(suffix);
+        for chunk in suffix_chunks.iter_mut() {
+            chunk.range = (chunk.range.start as isize + delta) as usize..(chunk.range.end as isize + delta) as usize;
+            chunk.lookahead = (chunk.lookahead as isize + delta) as usize;
+        }
+
+        let suffix_statements = self.chunks.iter().map(|chunk| chunk.statements).sum();
+        let mut program = std::mem::take(&mut self.tree.program);
+        let mut suffix_program = program.split_off(suffix_statements);
+        program.truncate(self.chunks[..prefix].iter().map(|chunk| chunk.statements).sum());
+        shift.visit_program_mut(&mut suffix_program);
+        program.append(&mut middle.program);
+        program.append(&mut suffix_program);
+
+        let suffix_errors = self.chunks.iter().map(|chunk| chunk.errors).sum();
+        let mut errors = std::mem::take(&mut self.tree.errors);
+        let mut suffix_errors = errors.split_off(suffix_errors);
+        errors.truncate(self.chunks[..prefix].iter().map(|chunk| chunk.errors).sum());
+        for error in suffix_errors.iter_mut() {
+            shift.error(error);
+        }
+        errors.append(&mut middle.errors);
+        errors.append(&mut suffix_errors);
+
+        let suffix_children = match suffix_chunks.is_empty() {
+            true => old_children.len(),
+            false => node_index(old_children, prefix_nodes(&self.chunks)),
+        };
+        let mut children: Vec<GreenElement> = old_children[..prefix_children].to_vec();
+        children.extend(middle.root.children.iter().cloned());
+        children.extend_from_slice(&old_children[suffix_children..]);
+
+        self.chunks.truncate(prefix);
+        self.chunks.append(&mut middle.chunks);
+        self.chunks.append(&mut suffix_chunks);
+        self.tree = SyntaxTree {
+            root: SyntaxNode::new_root(Rc::new(GreenNode::new(SyntaxKind::PROGRAM, children))),
+            program,
+            errors,
+        };
+        self.source = source;
+    }
+
+    // byte offset of an editor position, clamped to the line and the file
+    fn offset(&self, position: Position) -> usize {
+        let mut line_start = 0;
+        for _ in 0..position.line {
+            match self.source[line_start..].find('\n') {
+                Some(newline) => line_start += newline + 1,
+                None => return self.source.len(),
+            }
+        }
+        let line_end = self.source[line_start..].find('\n').map(|newline| line_start + newline).unwrap_or(self.source.len());
+        let mut offset = (line_start + position.character as usize).min(line_end);
+        while !self.source.is_char_boundary(offset) {
+            offset += 1;
+        }
+        offset
+    }
+}
+
+fn prefix_nodes(chunks: &[Chunk]) -> usize {
+    chunks.iter().map(|chunk| chunk.nodes).sum()
+}
+
+// index in `children` of the node that comes after `nodes` other nodes
+fn node_index(children: &[GreenElement], nodes: usize) -> usize {
+    children
+        .iter()
+        .enumerate()
+        .filter(|(_, child)| matches!(child, GreenElement::Node(_)))
+        .nth(nodes)
+        .map(|(index, _)| index)
+        .unwrap_or(children.len())
+}
+
+// start of the line `offset` is on, if there is only whitespace in front of it
+fn line_start(source: &str, offset: usize) -> Option<usize> {
+    let line_start = source[..offset].rfind('\n').map(|newline| newline + 1).unwrap_or(0);
+    match source[line_start..offset].bytes().all(|byte| byte == b' ' || byte == b'\t' || byte == b'\r') {
+        true => Some(line_start),
+        false => None,
+    }
+}
+
+// moves positions from where they were parsed to where they are in the file.
+// `lines` is added to every line, `columns` only to the columns on `line`
+struct Shift {
+    line: u32,
+    columns: u32,
+    lines: i64,
+}
+
+impl Shift {
+    fn position(&self, line: &mut u32, column: &mut u32) {
+        if *line == self.line {
+            *column += self.columns;
+        }
+        *line = (*line as i64 + self.lines) as u32;
+    }
+
+    fn span(&self, span: &mut Span) {
+        self.position(&mut span.start_line, &mut span.start_column);
+        self.position(&mut span.end_line, &mut span.end_column);
+    }
+
+    fn error(&self, error: &mut ParseError) {
+        for span in error.spans_mut() {
+            self.span(span);
+        }
+    }
+}
+
+impl VisitorMut for Shift {
+    fn visit_span_mut(&mut self, span: &mut Span) {
+        self.span(span);
+    }
+}
@@ -1,7 +1,22 @@
+pub mod arena;
+pub mod ast;
+pub mod cfg;
+pub mod dataflow;
+pub mod error;
+pub mod flow;
+pub mod fold;
 pub mod parser;
+pub mod printer;
+pub mod resolver;
 pub mod tokenizer;
+pub mod typecheck;
 pub mod lsp;
+pub mod incremental;
+pub mod lints;
+pub mod loader;
+pub mod syntax;
 pub mod types;
+pub mod visit;
 
 #[cfg(test)]
 mod tests;
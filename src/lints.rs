@@ -0,0 +1,116 @@
+// warnings about code that does nothing.
+//
+// a variable that is never read and an expression statement whose value is
+// thrown away are reported as warnings tagged `Unnecessary`, which editors show
+// by fading the code out. the expression body of a match arm counts as an
+// expression statement, a `match` has no value to hand it to. names starting
+// with an underscore are never reported, that is how a variable is marked as
+// unused on purpose. functions are left alone, one that isn't called here can
+// still be called by an importing file, and so are the names imports bring in.
+// parameters are part of a function's signature, so they aren't reported either.
+
+use std::collections::HashSet;
+
+use crate::error::SemanticError;
+use crate::parser::{Expr, Stmt};
+use crate::resolver::{DeclarationId, DeclarationKind, Resolution};
+use crate::tokenizer::Span;
+use crate::visit::{self, Visitor};
+
+pub fn check_unused(program: &[Stmt], resolution: &Resolution) -> Vec<SemanticError> {
+    let mut lints = Lints { writes: HashSet::new(), errors: Vec::new() };
+    lints.visit_program(program);
+
+    // assigning to a variable doesn't use its value, every other reference does
+    let read: HashSet<_> = resolution
+        .references
+        .iter()
+        .filter(|(span, _)| !lints.writes.contains(*span))
+        .map(|(_, declaration)| *declaration)
+        .collect();
+    for (index, declaration) in resolution.declarations.iter().enumerate() {
+        let exempt = declaration.name.starts_with('_')
+            || matches!(declaration.kind, DeclarationKind::Function | DeclarationKind::Parameter | DeclarationKind::Import);
+        if exempt || read.contains(&DeclarationId(index)) {
+            continue;
+        }
+        lints.errors.push(SemanticError::UnusedVariable { name: declaration.name.to_string(), span: declaration.span });
+    }
+
+    lints.errors.sort_by_key(|error| {
+        let span = error.span();
+        (span.start_line, span.start_column)
+    });
+    lints.errors
+}
+
+// whether evaluating `expr` can change anything, only assignments and calls can
+fn has_effect(expr: &Expr) -> bool {
+    match expr {
+        Expr::ASSIGNMENT(_, _) | Expr::CALL(_, _) => true,
+        // a syntax error has already been reported
+        Expr::Error => true,
+        Expr::PARENTHESIZED(inner) => has_effect(inner),
+        Expr::CONDITIONAL(_, then, otherwise) => has_effect(then) || has_effect(otherwise),
+        _ => false,
+    }
+}
+
+struct Lints {
+    // spans of the identifiers that are assigned to
+    writes: HashSet<Span>,
+    errors: Vec<SemanticError>,
+}
+
+impl Visitor for Lints {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::EXPR(expr, span) = stmt {
+            if !has_effect(expr) {
+                self.errors.push(SemanticError::UnusedResult { span: *span });
+            }
+        }
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::ASSIGNMENT(target, _) = expr {
+            if let Expr::IDENTIFIER(_, span) = target.as_ref() {
+                self.writes.insert(*span);
+            }
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Severity, Tag};
+    use crate::tests::{reported, resolved};
+    use crate::types::diagnostics::Diagnostic;
+
+    #[test]
+    fn test_unused_variables_and_results_are_reported() {
+        let source = "let PI = 3.14;\nlet radius = 5;\nif (radius > 0) {\n    let area = radius * PI;\n    let scale = 0.5;\n    scale = 1;\n}\nlet _ignored = 1;\nradius + 1;\n(f(radius));\nmatch (radius) { n => 0, m => f(m) }\nfunction g(x: int, y: int): int { return x; }";
+        let program = resolved(source);
+        let errors = check_unused(&program.tree.program, &program.resolution);
+
+        // `y` is never read either, but parameters are part of the signature
+        assert_eq!(
+            reported(&errors),
+            [
+                "4: W0201 unused variable 'area', prefix it with an underscore if this is intended",
+                "5: W0201 unused variable 'scale', prefix it with an underscore if this is intended",
+                "9: W0202 unused result, the value of this expression is never used",
+                "11: W0201 unused variable 'n', prefix it with an underscore if this is intended",
+                // a match has no value, so neither do the expressions its arms end in
+                "11: W0202 unused result, the value of this expression is never used",
+            ]
+        );
+        assert!(matches!(&errors[2], SemanticError::UnusedResult { span } if (span.start_column, span.end_column) == (1, 10)));
+        assert!(errors.iter().all(|error| error.severity() == Severity::Warning && error.tags() == [Tag::Unnecessary]));
+
+        let diagnostic = Diagnostic::from(&errors[0]);
+        assert_eq!((diagnostic.severity, diagnostic.tags), (2, vec![1]));
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::error::ParseError;
+use crate::parser::Stmt;
+use crate::syntax::{self, SyntaxTree};
+use crate::tokenizer::Span;
+
+// a file that couldn't be loaded, reported on the import statement naming it
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+    // the entry file itself couldn't be read, there is no import to point at
+    Unreadable { path: PathBuf, reason: String },
+    Unresolved { import: String, reason: String, span: Span },
+    // every file on the cycle in import order, ending with the one imported again
+    Cycle { cycle: Vec<PathBuf>, span: Span },
+}
+
+impl ImportError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ImportError::Unreadable { .. } => "E0101",
+            ImportError::Unresolved { .. } => "E0102",
+            ImportError::Cycle { .. } => "E0103",
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ImportError::Unreadable { .. } => None,
+            ImportError::Unresolved { span, .. } | ImportError::Cycle { span, .. } => Some(*span),
+        }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Unreadable { path, reason } => write!(f, "couldn't read {}: {}", path.display(), reason),
+            ImportError::Unresolved { import, reason, .. } => write!(f, "couldn't import {:?}: {}", import, reason),
+            ImportError::Cycle { cycle, .. } => {
+                let cycle: Vec<String> = cycle.iter().map(|path| path.display().to_string()).collect();
+                write!(f, "import cycle: {}", cycle.join(" -> "))
+            }
+        }
+    }
+}
+
+// a single parsed `.dod` file
+pub struct Module {
+    pub path: PathBuf,
+    pub program: Vec<Stmt>,
+    pub errors: Vec<ParseError>,
+    pub import_errors: Vec<ImportError>,
+    // resolved paths of every file this module imports, in source order
+    pub imports: Vec<PathBuf>,
+}
+
+// every module reachable from an entry file. `order` lists the modules so that
+// each one comes after everything it imports, which is the order they should
+// be analysed in
+pub struct ModuleGraph {
+    pub entry: PathBuf,
+    pub modules: HashMap<PathBuf, Module>,
+    pub order: Vec<PathBuf>,
+}
+
+impl ModuleGraph {
+    pub fn module(&self, path: &Path) -> Option<&Module> {
+        self.modules.get(path)
+    }
+}
+
+// loads `entry` and everything it imports from disk
+pub fn load_program(entry: &Path) -> ModuleGraph {
+    load_program_with(entry, |path| fs::read_to_string(path))
+}
+
+// like `load_program` but reads sources through `read`, which lets the editor
+// hand over unsaved buffers
+pub fn load_program_with<F>(entry: &Path, read: F) -> ModuleGraph
+where
+    F: Fn(&Path) -> io::Result<String>,
+{
+    let entry = normalize(entry);
+    let mut loader = Loader {
+        read,
+        modules: HashMap::new(),
+        order: Vec::new(),
+        stack: Vec::new(),
+    };
+
+    match (loader.read)(&entry) {
+        Ok(source) => loader.visit(&entry, &source),
+        Err(err) => {
+            // there is no importing file to report on, so the entry gets an empty module carrying the error
+            loader.modules.insert(
+                entry.clone(),
+                Module {
+                    path: entry.clone(),
+                    program: Vec::new(),
+                    errors: Vec::new(),
+                    import_errors: vec![ImportError::Unreadable { path: entry.clone(), reason: err.to_string() }],
+                    imports: Vec::new(),
+                },
+            );
+            loader.order.push(entry.clone());
+        }
+    }
+
+    ModuleGraph {
+        entry,
+        modules: loader.modules,
+        order: loader.order,
+    }
+}
+
+// resolves an import written in `importer` to the path of the imported file
+pub fn resolve_import(importer: &Path, import: &str) -> PathBuf {
+    let base = importer.parent().unwrap_or(Path::new(""));
+    normalize(&base.join(import))
+}
+
+// removes `.` and `..` components without touching the file system, so the
+// same file imported through different relative paths maps to a single module
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+struct Loader<F> {
+    read: F,
+    modules: HashMap<PathBuf, Module>,
+    order: Vec<PathBuf>,
+    // files currently being loaded, an import of one of them closes a cycle
+    stack: Vec<PathBuf>,
+}
+
+impl<F> Loader<F>
+where
+    F: Fn(&Path) -> io::Result<String>,
+{
+    fn visit(&mut self, path: &Path, source: &str) {
+        let SyntaxTree { program, errors, .. } = syntax::parse(source);
+        let mut import_errors = Vec::new();
+
+        self.stack.push(path.to_path_buf());
+        let mut imports = Vec::new();
+        // the parser rejects imports anywhere but the top level, so this is all of them
+        for stmt in &program {
+            if let Stmt::IMPORT(import, span) = stmt {
+                let resolved = resolve_import(path, import);
+
+                if let Some(position) = self.stack.iter().position(|p| *p == resolved) {
+                    let cycle = self.stack[position..].iter().chain(std::iter::once(&resolved)).cloned().collect();
+                    import_errors.push(ImportError::Cycle { cycle, span: *span });
+                    continue;
+                }
+
+                if !self.modules.contains_key(&resolved) {
+                    match (self.read)(&resolved) {
+                        Ok(source) => self.visit(&resolved, &source),
+                        Err(err) => {
+                            import_errors.push(ImportError::Unresolved {
+                                import: import.to_string(),
+                                reason: err.to_string(),
+                                span: *span,
+                            });
+                            continue;
+                        }
+                    }
+                }
+                imports.push(resolved);
+            }
+        }
+        self.stack.pop();
+
+        self.modules.insert(
+            path.to_path_buf(),
+            Module {
+                path: path.to_path_buf(),
+                program,
+                errors,
+                import_errors,
+                imports,
+            },
+        );
+        self.order.push(path.to_path_buf());
+    }
+}
@@ -1,9 +1,20 @@
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 //use compiler::lsp::{parse_notification, parse_request, serialize_response, Capabilities, DidOpenNotification, InitializeResult, Request, Response, ServerInfo};
-use compiler::parser::{Expr, Parser, Stmt};
-use compiler::tokenizer::{TokenKind, Tokenizer};
+use compiler::arena::Ast;
+use compiler::cfg::Cfg;
+use compiler::dataflow::check_initialized;
+use compiler::flow::check_flow;
+use compiler::fold::fold_constants;
+use compiler::incremental::Document;
+use compiler::loader::load_program_with;
+use compiler::parser::{Expr, Pattern, Stmt};
+use compiler::lints::check_unused;
+use compiler::resolver::{imported_names, resolve_with_imports, Options};
+use compiler::tokenizer::TokenKind;
+use compiler::typecheck::check_types;
 use compiler::types::diagnostics::{
     Diagnostic, PublishDiagnosticsNotification, PublishDiagnosticsParams,
 };
@@ -11,7 +22,9 @@ use compiler::types::did_change::{parse_change_notification, DidChangeNotificati
 use compiler::types::did_save::{parse_save_notification, DidSaveNotification};
 use compiler::types::{
     did_open::{parse_open_notification, DidOpenNotification},
-    initialization::{Capabilities, InitializationResponse, InitializeResult, ServerInfo},
+    initialization::{
+        Capabilities, InitializationOptions, InitializationResponse, InitializeRequest, InitializeResult, ServerInfo,
+    },
     parse_any, serialize_response, Request,
 };
 use compiler::types::{Position, Range};
@@ -34,6 +47,8 @@ fn main() -> io::Result<()> {
 
     
     let mut f = File::create("/home/mahmoud/compiler/src/log.txt")?;
+    let mut documents: HashMap<String, Document> = HashMap::new();
+    let mut options = InitializationOptions::default();
     loop {
         let msg = read_message();
         match msg {
@@ -47,6 +62,8 @@ fn main() -> io::Result<()> {
                     request.method
                 ))?;
                 if request.method == "initialize" {
+                    let initialize: InitializeRequest = parse_any(&msg).unwrap_or_default();
+                    options = initialize.params.initializationOptions.unwrap_or_default();
                     let response = InitializationResponse {
                         jsonrpc: String::from("2.0"),
                         id: request.id.unwrap_or(1),
@@ -55,7 +72,8 @@ fn main() -> io::Result<()> {
                                 name: String::from("customLSP"),
                             },
                             capabilities: Capabilities {
-                                textDocumentSync: 1,
+                                // incremental, edits only reparse the statements around them
+                                textDocumentSync: 2,
                                 documentHighlightProvider: true,
                                 colorProvider: true,
                             },
@@ -69,6 +87,10 @@ fn main() -> io::Result<()> {
                     f.write(b"\ninitialized\n")?;
                 } else if request.method == "textDocument/didOpen" {
                     let notification: DidOpenNotification = parse_open_notification(&msg);
+                    documents.insert(
+                        notification.params.textDocument.uri.clone(),
+                        Document::new(&notification.params.textDocument.text),
+                    );
                     //for token in tokens {
                     //    f.write_fmt(format_args!("\n\n {:?}", token))?;
                     //}
@@ -85,6 +107,9 @@ fn main() -> io::Result<()> {
                                 },
                                 severity: 3,
                                 message: String::from("your custom dod language server is running"),
+                                code: None,
+                                tags: Vec::new(),
+                                related_information: Vec::new(),
                             }],
                         },
                     };
@@ -94,19 +119,28 @@ fn main() -> io::Result<()> {
                     io::stdout().flush()?;
                 } else if request.method == "textDocument/didChange" {
                     let notification: DidChangeNotification = parse_change_notification(&msg);
-                    f.write_fmt(format_args!("/n/nrecieved changes: {}\n\n", notification.params.contentChanges.get(0).unwrap().text))?;
-                    let tokens = Tokenizer::tokenize(&notification.params.contentChanges.get(0).unwrap().text);
-                    f.write(b"\nextracted tokens\n")?;
-                    let mut parser = Parser::new(&tokens);
-                    parser.parse();
+                    let uri = notification.params.textDocument.uri.clone();
+                    for change in &notification.params.contentChanges {
+                        f.write_fmt(format_args!("/n/nrecieved changes: {}\n\n", change.text))?;
+                        match (change.range, documents.get_mut(&uri)) {
+                            (Some(range), Some(document)) => document.edit(range, &change.text),
+                            _ => {
+                                documents.insert(uri.clone(), Document::new(&change.text));
+                            }
+                        }
+                    }
                     f.write(b"\nfinished parsing\n")?;
+                    let diagnostics = match documents.get(&uri) {
+                        Some(document) => diagnostics(document, &uri, options),
+                        None => Vec::new(),
+                    };
                     let diagnostics = PublishDiagnosticsNotification {
                         method: String::from("textDocument/publishDiagnostics"),
                         jsonrpc: String::from("2.0"),
                         params: PublishDiagnosticsParams { 
                             uri: notification.params.textDocument.uri,
                             version: notification.params.textDocument.version,
-                            diagnostics: parser.diagnostics
+                            diagnostics
                         }
                     };
                     let stream = serialize_response(diagnostics);
@@ -153,10 +187,47 @@ fn read_message() -> io::Result<String> {
         .to_string());
 }
 
+// syntax errors followed by what the semantic passes found in the program
+fn diagnostics(document: &Document, uri: &str, options: InitializationOptions) -> Vec<Diagnostic> {
+    let tree = document.tree();
+    let mut diagnostics: Vec<Diagnostic> = tree.errors.iter().map(Diagnostic::from).collect();
+    // the open document is read from the editor, the files it imports from disk
+    let path = PathBuf::from(uri.strip_prefix("file://").unwrap_or(uri));
+    let graph = load_program_with(&path, |file: &Path| {
+        if file == path {
+            Ok(document.source().to_string())
+        } else {
+            fs::read_to_string(file)
+        }
+    });
+    let imported = imported_names(&graph, &path, &tree.program);
+    let resolution = resolve_with_imports(&tree.program, &imported, Options { warn_shadowing: options.warn_shadowing });
+    diagnostics.extend(resolution.errors.iter().map(Diagnostic::from));
+    diagnostics.extend(check_types(&tree.program, &resolution).errors.iter().map(Diagnostic::from));
+    let ast = Ast::from_program(&tree.program);
+    let folding = fold_constants(&ast, &resolution);
+    diagnostics.extend(folding.errors.iter().map(Diagnostic::from));
+    diagnostics.extend(check_flow(&ast, &folding).iter().map(Diagnostic::from));
+    let cfg = Cfg::from_program(&ast);
+    diagnostics.extend(check_initialized(&ast, &cfg, &resolution).iter().map(Diagnostic::from));
+    diagnostics.extend(check_unused(&tree.program, &resolution).iter().map(Diagnostic::from));
+    diagnostics.into_iter().map(|diagnostic| diagnostic.in_document(uri)).collect()
+}
+
 fn evaluate_expr_recursive(expr: &Expr, mut env: &mut HashMap<String, f32>) -> f32 {
     let value = match expr {
-        Expr::INTEGERLITERAL(n) => *n as f32,
-        Expr::FLOATLITERAL(n) => *n,
+        Expr::INTEGERLITERAL(n, _) => *n as f32,
+        Expr::FLOATLITERAL(n, _) => *n,
+        Expr::BOOLLITERAL(b, _) => {
+            if *b {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        Expr::STRINGLITERAL(_, _) => {
+            panic!("strings can't be evaluated to a number")
+        }
         Expr::UNARY(a) => {
             if evaluate_expr_recursive(a, &mut env) == 0.0 {
                 0.0
@@ -165,7 +236,7 @@ fn evaluate_expr_recursive(expr: &Expr, mut env: &mut HashMap<String, f32>) -> f
             }
         }
         Expr::PARENTHESIZED(a) => evaluate_expr_recursive(a, &mut env),
-        Expr::BINARYEXPR(left, right, token) => match token.kind {
+        Expr::BINARYEXPR(left, right, operator, _) => match operator {
             TokenKind::ADD => {
                 evaluate_expr_recursive(left, &mut env) + evaluate_expr_recursive(right, &mut env)
             }
@@ -185,13 +256,44 @@ fn evaluate_expr_recursive(expr: &Expr, mut env: &mut HashMap<String, f32>) -> f
                 panic!("unknown operator")
             }
         },
-        Expr::IDENTIFIER(identifier) => {
+        Expr::IDENTIFIER(identifier, _) => {
             if env.contains_key(identifier) {
                 return *env.get(identifier).unwrap();
             } else {
                 panic!("variable {:?} is referenced before declaration", identifier)
             }
         }
+        Expr::ARRAY(_) | Expr::INDEX(_, _) => {
+            panic!("arrays can't be evaluated to a number")
+        }
+        Expr::MEMBER(_, _) | Expr::CALL(_, _) | Expr::PATH(_) => {
+            panic!("members, calls and paths can't be evaluated yet")
+        }
+        Expr::STRUCTLITERAL(_, _) => {
+            panic!("structs can't be evaluated to a number")
+        }
+        Expr::Error => {
+            panic!("expressions with syntax errors can't be evaluated")
+        }
+        Expr::CONDITIONAL(condition, then, otherwise) => {
+            if evaluate_expr_recursive(condition, &mut env) != 0.0 {
+                evaluate_expr_recursive(then, &mut env)
+            } else {
+                evaluate_expr_recursive(otherwise, &mut env)
+            }
+        }
+        Expr::ASSIGNMENT(target, value) => {
+            let value = evaluate_expr_recursive(value, &mut env);
+            match target.as_ref() {
+                Expr::IDENTIFIER(identifier, _) => {
+                    env.insert(identifier.to_string(), value);
+                }
+                _ => {
+                    panic!("only variables can be assigned to during evaluation")
+                }
+            }
+            value
+        }
     };
 
     return value;
@@ -203,17 +305,23 @@ fn print_expr_recursive(expr: &Expr, level: i32) {
     }
 
     match expr {
-        Expr::INTEGERLITERAL(n) => {
+        Expr::INTEGERLITERAL(n, _) => {
             println!("{:?}", n);
         }
-        Expr::FLOATLITERAL(n) => {
+        Expr::FLOATLITERAL(n, _) => {
             println!("{:?}", n);
         }
-        Expr::IDENTIFIER(s) => {
+        Expr::STRINGLITERAL(s, _) => {
+            println!("{:?}", s);
+        }
+        Expr::BOOLLITERAL(b, _) => {
+            println!("{:?}", b);
+        }
+        Expr::IDENTIFIER(s, _) => {
             println!("{:?}", s);
         }
-        Expr::BINARYEXPR(a, b, c) => {
-            println!("{:?}", c.kind);
+        Expr::BINARYEXPR(a, b, c, _) => {
+            println!("{:?}", c);
             print_expr_recursive(a, level + 1);
             print_expr_recursive(b, level + 1);
         }
@@ -221,6 +329,63 @@ fn print_expr_recursive(expr: &Expr, level: i32) {
             println!("!");
             print_expr_recursive(a, level + 1);
         }
+        Expr::ARRAY(elements) => {
+            println!("[");
+            for element in elements {
+                print_expr_recursive(element, level + 1);
+            }
+            for _ in 0..level {
+                print!("--");
+            }
+            println!("]");
+        }
+        Expr::INDEX(a, b) => {
+            println!("[]");
+            print_expr_recursive(a, level + 1);
+            print_expr_recursive(b, level + 1);
+        }
+        Expr::ASSIGNMENT(a, b) => {
+            println!("=");
+            print_expr_recursive(a, level + 1);
+            print_expr_recursive(b, level + 1);
+        }
+        Expr::MEMBER(a, member) => {
+            println!(".{}", member);
+            print_expr_recursive(a, level + 1);
+        }
+        Expr::CALL(a, arguments) => {
+            println!("()");
+            print_expr_recursive(a, level + 1);
+            for argument in arguments {
+                print_expr_recursive(argument, level + 1);
+            }
+        }
+        Expr::PATH(segments) => {
+            println!("{}", segments.join("::"));
+        }
+        Expr::Error => {
+            println!("<error>");
+        }
+        Expr::CONDITIONAL(a, b, c) => {
+            println!("?:");
+            print_expr_recursive(a, level + 1);
+            print_expr_recursive(b, level + 1);
+            print_expr_recursive(c, level + 1);
+        }
+        Expr::STRUCTLITERAL(name, fields) => {
+            println!("{} {{", name);
+            for field in fields {
+                for _ in 0..level + 1 {
+                    print!("--");
+                }
+                println!("{}:", field.name);
+                print_expr_recursive(&field.value, level + 2);
+            }
+            for _ in 0..level {
+                print!("--");
+            }
+            println!("}}");
+        }
         Expr::PARENTHESIZED(a) => {
             println!("(");
             print_expr_recursive(a, level + 1);
@@ -239,20 +404,72 @@ fn print_stmt(stmt: &Stmt, level: i32) {
     }
 
     match stmt {
-        Stmt::VariableDeclaration(identifier, expr) => {
-            println!("let {:?} = ", identifier);
-            print_expr_recursive(expr, level + 1);
+        Stmt::VariableDeclaration(declaration) => {
+            let keyword = if declaration.constant { "const" } else { "let" };
+            match &declaration.annotation {
+                Some(annotation) => println!("{} {:?}: {} = ", keyword, declaration.name, annotation.name),
+                None => println!("{} {:?} = ", keyword, declaration.name),
+            }
+            if let Some(initializer) = &declaration.initializer {
+                print_expr_recursive(initializer, level + 1);
+            }
         }
-        Stmt::EXPR(expr) => {
+        Stmt::StructDeclaration(name, fields) => {
+            println!("struct {:?} {{", name);
+            for field in fields {
+                for _ in 0..level + 1 {
+                    print!("--");
+                }
+                println!("{}: {}", field.name, field.type_name);
+            }
+            for _ in 0..level {
+                print!("--");
+            }
+            println!("}}");
+        }
+        Stmt::FunctionDeclaration(function) => {
+            let parameters: Vec<String> = function
+                .parameters
+                .iter()
+                .map(|parameter| format!("{}: {}", parameter.name, parameter.annotation.name))
+                .collect();
+            match &function.return_type {
+                Some(return_type) => println!("function {:?}({}): {}", function.name, parameters.join(", "), return_type.name),
+                None => println!("function {:?}({})", function.name, parameters.join(", ")),
+            }
+            print_stmt(&function.body, level);
+        }
+        Stmt::EXPR(expr, _) => {
             print_expr_recursive(expr, level + 1);
         }
-        Stmt::IFSTATEMENT(expr, stmts) => {
+        Stmt::IFSTATEMENT(expr, body) => {
             println!("if (");
             print_expr_recursive(expr, level + 1);
             for _ in 0..level {
                 print!("--");
             }
-            println!("){{");
+            println!(")");
+            print_stmt(body, level);
+        },
+        Stmt::WHILE(expr, body) => {
+            println!("while (");
+            print_expr_recursive(expr, level + 1);
+            for _ in 0..level {
+                print!("--");
+            }
+            println!(")");
+            print_stmt(body, level);
+        },
+        Stmt::RETURN(value, _) => {
+            println!("return");
+            if let Some(value) = value {
+                print_expr_recursive(value, level + 1);
+            }
+        },
+        Stmt::BREAK(_) => println!("break"),
+        Stmt::CONTINUE(_) => println!("continue"),
+        Stmt::Block(stmts) => {
+            println!("{{");
             for stmt in stmts {
                 print_stmt(stmt, level + 1);
                 println!("___________");
@@ -262,6 +479,40 @@ fn print_stmt(stmt: &Stmt, level: i32) {
             }
             println!("}}");
         },
+        Stmt::MATCH(expr, arms) => {
+            println!("match (");
+            print_expr_recursive(expr, level + 1);
+            for _ in 0..level {
+                print!("--");
+            }
+            println!("){{");
+            for arm in arms {
+                for _ in 0..level + 1 {
+                    print!("--");
+                }
+                match &arm.pattern {
+                    Pattern::Literal(literal, _) => {
+                        println!("=>");
+                        print_expr_recursive(literal, level + 2);
+                    }
+                    Pattern::Wildcard(_) => {
+                        println!("_ =>");
+                    }
+                    Pattern::Binding(name, _) => {
+                        println!("{} =>", name);
+                    }
+                }
+                print_stmt(&arm.body, level + 2);
+                println!("___________");
+            }
+            for _ in 0..level {
+                print!("--");
+            }
+            println!("}}");
+        }
+        Stmt::IMPORT(path, _) => {
+            println!("import {:?}", path);
+        }
         Stmt::EOF => {
             println!("None Variant");
         }
@@ -1,461 +1,1340 @@
-use std::{iter::Peekable, slice::Iter};
+use std::{cell::Cell, ops::Range};
 
-use crate::types::{diagnostics::Diagnostic, Position, Range};
+use serde::{Deserialize, Serialize};
 
-use super::tokenizer::{Token, TokenKind};
+use crate::arena::{
+    BinaryOp, ExprId, ExprNode, FieldDeclarationNode, FieldInitializerNode, FunctionDeclarationNode, ListRange,
+    MatchArmNode, ParameterNode, PatternNode, Sink, StmtId, StmtNode, Symbol, TypeAnnotationNode,
+    VariableDeclarationNode,
+};
+use crate::syntax::{Event, SyntaxKind};
+use crate::error::{Expected, ParseError};
 
+use super::tokenizer::{Span, Token, TokenKind};
+
+// the AST converts to and from JSON with serde. enums are tagged with the variant
+// name (`{"BOOLLITERAL": [true, {...}]}`, unit variants are plain strings like `"Error"`),
+// tuple variants hold an array of their fields and structs are objects keyed by
+// field name. tokens and spans keep their 1 based lines and columns
+
+// `name: type` inside a struct declaration, the span covers both the name and the type
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldDeclaration {
+    pub name: String,
+    pub type_name: String,
+    pub span: Span
+}
+
+// `name: value` inside a struct literal, the span covers the field name
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldInitializer {
+    pub name: String,
+    pub value: Expr,
+    pub span: Span
+}
+
+// `: type` after the name in a variable declaration
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct TypeAnnotation {
+    pub name: String,
+    pub span: Span
+}
+
+// `let name: type = initializer;`, or `const` in place of `let` for a binding
+// that can't be assigned to. a `let` with a type can leave out the initializer
+// and be assigned later. the span covers the name
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct VariableDeclaration {
+    pub name: String,
+    pub span: Span,
+    pub constant: bool,
+    pub annotation: Option<TypeAnnotation>,
+    pub initializer: Option<Expr>
+}
+
+// `name: type` in the parameter list of a function, the span covers the name
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Parameter {
+    pub name: String,
+    pub span: Span,
+    pub annotation: TypeAnnotation
+}
+
+// `function name(parameter: type, ...): type { body }`, a function without a
+// return type returns void. the span covers the name, the signature everything
+// from `function` up to the return type
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct FunctionDeclaration {
+    pub name: String,
+    pub span: Span,
+    pub signature: Span,
+    pub parameters: Vec<Parameter>,
+    pub return_type: Option<TypeAnnotation>,
+    pub body: Box<Stmt>
+}
+
+// patterns accepted on the left of `=>` in a match arm
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum Pattern {
+    Literal(Expr, Span),
+    Wildcard(Span),
+    Binding(String, Span),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Stmt
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum Stmt{
     EOF,
-    VariableDeclaration(String, Expr),
-    IFSTATEMENT(Expr, Vec<Stmt>),
-    EXPR(Expr),
+    VariableDeclaration(VariableDeclaration),
+    StructDeclaration(String, Vec<FieldDeclaration>),
+    FunctionDeclaration(FunctionDeclaration),
+    IFSTATEMENT(Expr, Box<Stmt>),
+    WHILE(Expr, Box<Stmt>),
+    // the spans of `return`, `break` and `continue` cover the keyword
+    RETURN(Option<Expr>, Span),
+    BREAK(Span),
+    CONTINUE(Span),
+    Block(Vec<Stmt>),
+    MATCH(Expr, Vec<MatchArm>),
+    IMPORT(String, Span),
+    // the span covers the expression
+    EXPR(Expr, Span),
 }
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum Expr {  
-    INTEGERLITERAL(i32),
-    FLOATLITERAL(f32),
-    IDENTIFIER(String),
-    BINARYEXPR(Box<Expr>, Box<Expr>, Token),
+    INTEGERLITERAL(i32, Span),
+    FLOATLITERAL(f32, Span),
+    STRINGLITERAL(String, Span),
+    BOOLLITERAL(bool, Span),
+    IDENTIFIER(String, Span),
+    // the span is the operator's
+    BINARYEXPR(Box<Expr>, Box<Expr>, TokenKind, Span),
     PARENTHESIZED(Box<Expr>),
-    UNARY(Box<Expr>)
+    UNARY(Box<Expr>),
+    ARRAY(Vec<Expr>),
+    INDEX(Box<Expr>, Box<Expr>),
+    ASSIGNMENT(Box<Expr>, Box<Expr>),
+    MEMBER(Box<Expr>, String),
+    CALL(Box<Expr>, Vec<Expr>),
+    PATH(Vec<String>),
+    STRUCTLITERAL(String, Vec<FieldInitializer>),
+    CONDITIONAL(Box<Expr>, Box<Expr>, Box<Expr>),
+    // placeholder for an expression that couldn't be parsed or is missing
+    Error,
+}
+
+impl Expr {
+    // the source the expression covers as far as its parts record it. paths, empty
+    // arrays and error nodes don't keep any position
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Expr::INTEGERLITERAL(_, span)
+            | Expr::FLOATLITERAL(_, span)
+            | Expr::STRINGLITERAL(_, span)
+            | Expr::BOOLLITERAL(_, span)
+            | Expr::IDENTIFIER(_, span) => Some(*span),
+            Expr::BINARYEXPR(left, right, _, _) | Expr::INDEX(left, right) | Expr::ASSIGNMENT(left, right) => {
+                covering(left.span(), right.span())
+            }
+            Expr::CONDITIONAL(condition, _, otherwise) => covering(condition.span(), otherwise.span()),
+            Expr::PARENTHESIZED(inner) | Expr::UNARY(inner) | Expr::MEMBER(inner, _) => inner.span(),
+            Expr::ARRAY(elements) => covering(
+                elements.first().and_then(|first| first.span()),
+                elements.last().and_then(|last| last.span()),
+            ),
+            Expr::CALL(callee, arguments) => {
+                covering(callee.span(), arguments.last().and_then(|last| last.span()))
+            }
+            Expr::STRUCTLITERAL(_, fields) => covering(
+                fields.first().map(|first| first.span),
+                fields.last().and_then(|last| last.value.span().or(Some(last.span))),
+            ),
+            Expr::PATH(_) | Expr::Error => None,
+        }
+    }
+}
+
+// the span from `start` to `end`, or whichever of them is known
+pub(crate) fn covering(start: Option<Span>, end: Option<Span>) -> Option<Span> {
+    match (start, end) {
+        (Some(start), Some(end)) => Some(start.to(end)),
+        (start, end) => start.or(end),
+    }
 }
 
 
-pub struct Parser<'a>{
-    tokenizer: Peekable<Iter<'a, Token>>,
-    pub program: Vec<Stmt>,
-    pub diagnostics: Vec<Diagnostic>
+// walks the token list for the parser and records every consumed token, along
+// with the node boundaries the parser reports, as events for the syntax tree
+struct TokenCursor<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    events: Vec<Event>,
+    open_nodes: usize,
+    // index of the furthest token the parser has looked at
+    furthest: Cell<usize>
+}
+
+impl<'a> TokenCursor<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.furthest.set(self.furthest.get().max(self.position));
+        return self.tokens.get(self.position);
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position)?;
+        self.events.push(Event::Token(self.position));
+        self.position += 1;
+        return Some(token);
+    }
+}
+
+// keywords that start a statement, a broken statement or expression never swallows them
+const STATEMENT_KEYWORDS: [&str; 11] =
+    ["let", "const", "if", "while", "return", "break", "continue", "struct", "function", "match", "import"];
+
+// what one top level iteration of `Parser::parse` consumed and produced. its
+// output only depends on the tokens up to `lookahead`, so an edit after that
+// token leaves it unchanged, which is what incremental reparsing relies on
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub tokens: Range<usize>,
+    // index of the last token the parser looked at, consumed or not
+    pub lookahead: usize,
+    pub statements: Range<usize>,
+    pub errors: Range<usize>,
+    // number of nodes the chunk put directly under the root of the syntax tree
+    pub nodes: usize
+}
+
+// what a match arm matches, as far as telling whether an earlier arm covers it goes
+enum Coverage<'a> {
+    Literal(&'a TokenKind),
+    Everything,
+    // a token that isn't a pattern
+    Nothing,
+}
+
+pub struct Parser<'a, S: Sink = ()>{
+    tokenizer: TokenCursor<'a>,
+    // receives the AST nodes, see `Sink`
+    pub sink: S,
+    pub errors: Vec<ParseError>,
+    pub chunks: Vec<Chunk>,
+    // number of statements parsed at the top of the file
+    statements: usize,
+    // number of blocks around the statement being parsed, 0 at the top of the file
+    depth: usize
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokens: &'a Vec<Token>) -> Parser {
+        Parser::with_sink(tokens, ())
+    }
+}
+
+impl<'a, S: Sink> Parser<'a, S> {
+    // a parser that emits the AST into `sink` as it goes, `arena::Ast::parse`
+    // uses this to build the arena without going through the boxed AST
+    pub fn with_sink(tokens: &'a Vec<Token>, sink: S) -> Parser<'a, S> {
         Parser {
-            tokenizer: tokens.iter().peekable(),
-            program: Vec::new(),
-            diagnostics: Vec::new()
+            tokenizer: TokenCursor { tokens, position: 0, events: Vec::new(), open_nodes: 0, furthest: Cell::new(0) },
+            sink,
+            errors: Vec::new(),
+            chunks: Vec::new(),
+            statements: 0,
+            depth: 0
         }
     }
 
+    // the events recorded so far, `syntax::build_tree` turns them into a syntax tree
+    pub fn events(&self) -> &[Event] {
+        return &self.tokenizer.events;
+    }
+
+    fn start_node(&mut self, kind: SyntaxKind) {
+        self.tokenizer.events.push(Event::Start(kind));
+        self.tokenizer.open_nodes += 1;
+    }
 
-    fn parse_stmt(&mut self) -> Result<Stmt, Diagnostic> {
-        let token = self.tokenizer.peek();
-        match token {
-            Some(token) => match &token.kind {
+    fn finish_node(&mut self) {
+        self.tokenizer.events.push(Event::Finish);
+        self.tokenizer.open_nodes -= 1;
+    }
+
+    // marks the current position so a node can later be started there, this is
+    // how a binary expression ends up wrapping its already parsed left operand
+    fn checkpoint(&self) -> usize {
+        return self.tokenizer.events.len();
+    }
+
+    fn start_node_at(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        self.tokenizer.events.insert(checkpoint, Event::Start(kind));
+        self.tokenizer.open_nodes += 1;
+    }
+
+    // the token being looked at, the token list always ends with EOF so that is
+    // what is returned once everything has been consumed
+    fn current(&self) -> &'a Token {
+        match self.tokenizer.peek() {
+            Some(token) => token,
+            None => self.tokenizer.tokens.last().unwrap()
+        }
+    }
+
+    fn at(&self, kind: TokenKind) -> bool {
+        return std::mem::discriminant(&self.current().kind) == std::mem::discriminant(&kind);
+    }
+
+    // tokens a broken expression stops in front of instead of consuming them, so
+    // the surrounding statement or list can pick up from there
+    fn at_recovery_point(&self) -> bool {
+        match &self.current().kind {
+            TokenKind::SEMICOLON | TokenKind::RPAREN | TokenKind::RSQUARE | TokenKind::RCURLY | TokenKind::COMMA
+                | TokenKind::COLON | TokenKind::FATARROW | TokenKind::EOF => true,
+            TokenKind::KEYWORD(keyword) => STATEMENT_KEYWORDS.contains(&keyword.as_str()),
+            _ => false
+        }
+    }
+
+    fn error(&mut self, error: ParseError) {
+        self.errors.push(error);
+    }
+
+    // wraps the current token in an error node, EOF is never consumed
+    fn skip_token(&mut self) {
+        if self.at(TokenKind::EOF) {
+            return;
+        }
+        self.start_node(SyntaxKind::ERROR);
+        self.tokenizer.next();
+        self.finish_node();
+    }
+
+    // loops over statements and list items call this to make sure every
+    // iteration consumes something, even when the code is too broken to parse
+    fn skip_if_stuck(&mut self, position: usize) {
+        if self.tokenizer.position == position {
+            self.skip_token();
+        }
+    }
+
+    // after a statement failed to parse: closes the nodes it left open and skips
+    // to the end of it, which is after the next `;` or before the next `}` or
+    // statement keyword. braces opened while skipping are skipped as a whole
+    fn recover(&mut self, open_nodes: usize) {
+        while self.tokenizer.open_nodes > open_nodes {
+            self.finish_node();
+        }
+
+        self.start_node(SyntaxKind::ERROR);
+        let mut depth = 0;
+        loop {
+            match &self.current().kind {
                 TokenKind::EOF => {
+                    break;
+                }
+                TokenKind::SEMICOLON if depth == 0 => {
+                    self.tokenizer.next();
+                    break;
+                }
+                TokenKind::RCURLY if depth == 0 => {
+                    break;
+                }
+                TokenKind::KEYWORD(keyword) if depth == 0 && STATEMENT_KEYWORDS.contains(&keyword.as_str()) => {
+                    break;
+                }
+                TokenKind::LCURLY => {
+                    depth += 1;
                     self.tokenizer.next();
-                    return Ok(Stmt::EOF);
                 }
-                TokenKind::KEYWORD(keyword) => {
-                    if keyword == "let" {
-                        let stmt = self.parse_variable_declaration()?;
-                        return Ok(stmt);
-                    } else if keyword == "if" {
-                        // consume if
-                        let previous = self.tokenizer.next().unwrap();
-                        //parse paren
-                        let token = self.tokenizer.next();
-                        match token {
-                            Some(token) => {
-                                match token.kind {
-                                    TokenKind::LPAREN => {},
-                                    _ => {
-                                        let diagnostic = Diagnostic {
-                                            range: Range {
-                                                start: Position { line: token.line - 1, character: token.column - 1 },
-                                                end: Position { line: token.line - 1, character: token.column }
-                                            },
-                                            severity: 1,
-                                            message: format_args!("invalid syntax at line {:?} for if statement, expected '(' at column {:?} found {:?}", token.line, token.column, token.kind).to_string() 
-                                        };
-                                        return Err(diagnostic);
-                                    }
-                                }
-                            },
-                            None => {
-                                let diagnostic = Diagnostic {
-                                    range: Range {
-                                        start: Position { line: previous.line - 1, character: previous.column + 1 },
-                                        end: Position { line: previous.line - 1, character: previous.column + 2 }
-                                    },
-                                    severity: 1,
-                                    message: format_args!("invalid syntax at line {:?} for if statement, expected '(' at column {:?} found 'EOF'", previous.line, previous.column + 2).to_string() 
-                                };
-                                return Err(diagnostic);
-                            }
-                        }
-                        //parse expr
-                        let expr = self.parse_expr()?;
-                        //parse paren
-                        let token = self.tokenizer.next();
-
-                        match token {
-                            Some(token) => {
-                                match token.kind {
-                                    TokenKind::RPAREN => {},
-                                    _ => {
-
-                                        let diagnostic = Diagnostic {
-                                            range: Range {
-                                                start: Position { line: token.line - 1, character: token.column - 1 },
-                                                end: Position { line: token.line - 1, character: token.column }
-                                            },
-                                            severity: 1,
-                                            message: format_args!("invalid syntax at line {:?} for if statement, expected ')' at column {:?} found {:?}", token.line, token.column, token.kind).to_string() 
-                                        };
-                                        return Err(diagnostic);
-                                    }
-                                }
-                            },
-                            None => {
-                                let diagnostic = Diagnostic {
-                                    range: Range {
-                                        start: Position { line: previous.line - 1, character: previous.column + 1 },
-                                        end: Position { line: previous.line - 1, character: previous.column + 2 }
-                                    },
-                                    severity: 1,
-                                    message: format_args!("invalid syntax at line {:?} for if statement, expected '(' at column {:?} found 'EOF'", previous.line, previous.column + 2).to_string() 
-                                };
-                                return Err(diagnostic);
-                            }
-                        };
-
-                        let token = self.tokenizer.next();
-
-                        match token {
-                            Some(token) => {
-                                match token.kind {
-                                    TokenKind::LCURLY => {},
-                                    _ => {
-
-                                        let diagnostic = Diagnostic {
-                                            range: Range {
-                                                start: Position { line: token.line - 1, character: token.column - 1 },
-                                                end: Position { line: token.line - 1, character: token.column }
-                                            },
-                                            severity: 1,
-                                            message: format_args!("invalid syntax at line {:?} for if statement, expected '{{' at column {:?} found {:?}", token.line, token.column, token.kind).to_string() 
-                                        };
-                                        return Err(diagnostic);
-                                    }
-                                }
-                            },
-                            None => {
-                                let diagnostic = Diagnostic {
-                                    range: Range {
-                                        start: Position { line: previous.line - 1, character: previous.column + 1 },
-                                        end: Position { line: previous.line - 1, character: previous.column + 2 }
-                                    },
-                                    severity: 1,
-                                    message: format_args!("invalid syntax at line {:?} for if statement, expected '(' at column {:?} found 'EOF'", previous.line, previous.column + 2).to_string() 
-                                };
-                                return Err(diagnostic);
-                            }
-                        }
-
-                        //parse statements
-                        let mut statements = Vec::new();
-                        loop {
-                            let statement = self.parse_stmt()?;
-                            statements.push(statement);
-                            let token = self.tokenizer.peek();
-                            if let Some(token) = token {
-                                if let TokenKind::RCURLY = token.kind {
-                                    break;
-                                }
-
-                                if let TokenKind::EOF = token.kind {
-                                    
-                                    let diagnostic = Diagnostic {
-                                        range: Range {
-                                            start: Position { line: previous.line - 1, character: previous.column + 1 },
-                                            end: Position { line: previous.line - 1, character: previous.column + 2 }
-                                        },
-                                        severity: 1,
-                                        message: format_args!("invalid syntax at line {:?} for if statement, expected ')' at column {:?} found 'EOF'", previous.line, previous.column + 2).to_string() 
-                                    };
-                                    return Err(diagnostic);
-                                }
-                            } else {
-                                let diagnostic = Diagnostic {
-                                    range: Range {
-                                        start: Position { line: previous.line - 1, character: previous.column + 1 },
-                                        end: Position { line: previous.line - 1, character: previous.column + 2 }
-                                    },
-                                    severity: 1,
-                                    message: format_args!("invalid syntax at line {:?} for if statement, expected '(' at column {:?} found 'EOF'", previous.line, previous.column + 2).to_string() 
-                                };
-                                return Err(diagnostic);
-                            }
-                        }
-                        // consume closing curly
-                        self.tokenizer.next().unwrap();
-                        return Ok(Stmt::IFSTATEMENT(expr, statements));
-                    } else {
-                        return Err(Diagnostic {
-                            range: Range {
-                                start: Position {line: token.line - 1, character: token.column - 1},
-                                end:  Position {line: token.line - 1, character: token.column },
-                            },
-                            severity: 1,
-                            message:format_args!("unimplemented keyword, '{:?}'", keyword).to_string() 
-                        });
+                TokenKind::RCURLY => {
+                    // a brace group closing ends the broken statement, `while (x) { }` is skipped as a whole
+                    depth -= 1;
+                    self.tokenizer.next();
+                    if depth == 0 {
+                        break;
                     }
                 }
                 _ => {
-                    let expr = self.parse_expr()?;
-                    return Ok(Stmt::EXPR(expr));
+                    self.tokenizer.next();
+                }
+            }
+        }
+        self.finish_node();
+    }
+
+    // an expression that is absent altogether, e.g. the initializer in `let x;`
+    fn missing_expr(&mut self) -> ExprId {
+        self.start_node(SyntaxKind::ERROR);
+        self.finish_node();
+        return self.sink.expr(ExprNode::Error);
+    }
+
+    fn parse_stmt(&mut self) -> Result<StmtId, ParseError> {
+        let token = self.current();
+        match &token.kind {
+            TokenKind::KEYWORD(keyword) => {
+                if keyword == "let" || keyword == "const" {
+                    let stmt = self.parse_variable_declaration()?;
+                    return Ok(stmt);
+                } else if keyword == "struct" {
+                    let stmt = self.parse_struct_declaration()?;
+                    return Ok(stmt);
+                } else if keyword == "function" {
+                    let stmt = self.parse_function_declaration()?;
+                    return Ok(stmt);
+                } else if keyword == "import" {
+                    let stmt = self.parse_import()?;
+                    return Ok(stmt);
+                } else if keyword == "match" {
+                    let stmt = self.parse_match()?;
+                    return Ok(stmt);
+                } else if keyword == "true" || keyword == "false" {
+                    let stmt = self.parse_expression_statement()?;
+                    return Ok(stmt);
+                } else if keyword == "if" {
+                    let stmt = self.parse_if_statement()?;
+                    return Ok(stmt);
+                } else if keyword == "while" {
+                    let stmt = self.parse_while_statement()?;
+                    return Ok(stmt);
+                } else if keyword == "return" {
+                    let stmt = self.parse_return_statement()?;
+                    return Ok(stmt);
+                } else if keyword == "break" || keyword == "continue" {
+                    let stmt = self.parse_jump_statement();
+                    return Ok(stmt);
+                } else {
+                    return Err(ParseError::UnknownKeyword { keyword: keyword.to_string(), span: token.span() });
                 }
-            },
-            None => return Ok(Stmt::EOF)
+            }
+            TokenKind::LCURLY => {
+                let block = self.parse_block()?;
+                return Ok(block);
+            }
+            TokenKind::SEMICOLON | TokenKind::RPAREN | TokenKind::RSQUARE | TokenKind::RCURLY | TokenKind::COMMA
+                | TokenKind::COLON | TokenKind::FATARROW => {
+                return Err(ParseError::unexpected(token, vec![Expected::Statement], "statement"));
+            }
+            _ => {
+                let stmt = self.parse_expression_statement()?;
+                return Ok(stmt);
+            }
         }
     }
 
+    fn parse_expression_statement(&mut self) -> Result<StmtId, ParseError> {
+        self.start_node(SyntaxKind::EXPR_STMT);
+        let start = self.tokenizer.position;
+        let expr = self.parse_expr()?;
+        let span = self.span_from(start);
+        // the `;` after an expression statement has always been optional, sources
+        // written before the other statements needed one still parse the same
+        if self.at(TokenKind::SEMICOLON) {
+            self.tokenizer.next();
+        }
+        self.finish_node();
+        return Ok(self.sink.stmt(StmtNode::EXPR(expr, span)));
+    }
 
+    // the span of the tokens consumed since the token at `start`, or of the
+    // current token when nothing was consumed
+    fn span_from(&self, start: usize) -> Span {
+        let end = self.tokenizer.position;
+        if end == start {
+            return self.current().span();
+        }
+        let tokens = self.tokenizer.tokens;
+        return tokens[start].span().to(tokens[end - 1].span());
+    }
+
+    // parses the whole token list. errors don't stop the parser, the broken
+    // statement is reported and skipped and parsing resumes after it
     pub fn parse(&mut self) {
         loop {
+            if self.at(TokenKind::EOF) {
+                self.tokenizer.next();
+                break;
+            }
+
+            let position = self.tokenizer.position;
+            let statements = self.statements;
+            let errors = self.errors.len();
+            let events = self.tokenizer.events.len();
             match self.parse_stmt() {
                 Ok(stmt) => {
-                    match stmt {
-                        Stmt::EOF => { break; },
-                        _ => { self.program.push(stmt); }
-                    }
+                    self.sink.top_level(stmt);
+                    self.statements += 1;
                 },
-                Err (diagnostic) => {
-                    self.diagnostics.push(diagnostic);
-                    break;
+                Err(error) => {
+                    self.errors.push(error);
+                    self.recover(0);
                 }
             }
+            self.skip_if_stuck(position);
+
+            let nodes = self.count_root_nodes(events);
+            self.chunks.push(Chunk {
+                tokens: position..self.tokenizer.position,
+                lookahead: self.tokenizer.furthest.get().max(self.tokenizer.position - 1),
+                statements: statements..self.statements,
+                errors: errors..self.errors.len(),
+                nodes
+            });
         }
     }
 
-    fn parse_variable_declaration(&mut self) -> Result<Stmt, Diagnostic> {
-        // consume let or const
+    // nodes opened at the top level since the event at `from`
+    fn count_root_nodes(&self, from: usize) -> usize {
+        let mut depth = 0;
+        let mut nodes = 0;
+        for event in &self.tokenizer.events[from..] {
+            match event {
+                Event::Start(_) => {
+                    if depth == 0 {
+                        nodes += 1;
+                    }
+                    depth += 1;
+                }
+                Event::Finish => {
+                    depth -= 1;
+                }
+                Event::Token(_) => {}
+            }
+        }
+        return nodes;
+    }
+
+    fn parse_if_statement(&mut self) -> Result<StmtId, ParseError> {
+        self.start_node(SyntaxKind::IF_STMT);
+        // consume if
         self.tokenizer.next().unwrap();
-        // consume identifier
-        let identifier = self.tokenizer.next().unwrap();
-        let identifier = match &identifier.kind {
-            TokenKind::IDENTIFIER(a) => {
-                a
+        self.expect(TokenKind::LPAREN, "if statement");
+        let expr = self.parse_expr()?;
+        self.expect(TokenKind::RPAREN, "if statement");
+        let body = self.parse_block()?;
+        self.finish_node();
+        return Ok(self.sink.stmt(StmtNode::IFSTATEMENT(expr, body)));
+    }
+
+    fn parse_while_statement(&mut self) -> Result<StmtId, ParseError> {
+        self.start_node(SyntaxKind::WHILE_STMT);
+        // consume while
+        self.tokenizer.next().unwrap();
+        self.expect(TokenKind::LPAREN, "while statement");
+        let expr = self.parse_expr()?;
+        self.expect(TokenKind::RPAREN, "while statement");
+        let body = self.parse_block()?;
+        self.finish_node();
+        return Ok(self.sink.stmt(StmtNode::WHILE(expr, body)));
+    }
+
+    // `return;` or `return value;`
+    fn parse_return_statement(&mut self) -> Result<StmtId, ParseError> {
+        self.start_node(SyntaxKind::RETURN_STMT);
+        let keyword = self.tokenizer.next().unwrap();
+        let value = if self.at_recovery_point() {
+            None
+        } else {
+            Some(self.parse_expr()?)
+        };
+        self.consume_semicolon();
+        self.finish_node();
+        return Ok(self.sink.stmt(StmtNode::RETURN(value, keyword.span())));
+    }
+
+    // `break;` and `continue;`
+    fn parse_jump_statement(&mut self) -> StmtId {
+        let keyword = self.current();
+        let is_break = keyword.kind == TokenKind::KEYWORD(String::from("break"));
+        self.start_node(if is_break { SyntaxKind::BREAK_STMT } else { SyntaxKind::CONTINUE_STMT });
+        self.tokenizer.next();
+        self.consume_semicolon();
+        self.finish_node();
+        match is_break {
+            true => self.sink.stmt(StmtNode::BREAK(keyword.span())),
+            false => self.sink.stmt(StmtNode::CONTINUE(keyword.span())),
+        }
+    }
+
+    // parses `{ stmt* }`, every construct that owns a body (if, loops, functions)
+    // goes through here so braces are handled in one place
+    fn parse_block(&mut self) -> Result<StmtId, ParseError> {
+        self.start_node(SyntaxKind::BLOCK);
+        let opening = match self.expect(TokenKind::LCURLY, "block") {
+            Some(opening) => opening,
+            None => {
+                self.finish_node();
+                let statements = self.sink.stmt_list(&[]);
+                return Ok(self.sink.stmt(StmtNode::Block(statements)));
+            }
+        };
+
+        let mut statements = Vec::new();
+        self.depth += 1;
+        loop {
+            if self.at(TokenKind::RCURLY) {
+                // consume closing curly
+                self.tokenizer.next();
+                break;
+            }
+
+            if self.at(TokenKind::EOF) {
+                self.error(ParseError::UnclosedDelimiter {
+                    delimiter: TokenKind::RCURLY,
+                    opening: opening.span(),
+                    found: TokenKind::EOF,
+                    span: opening.span()
+                });
+                break;
+            }
+
+            let position = self.tokenizer.position;
+            let open_nodes = self.tokenizer.open_nodes;
+            match self.parse_stmt() {
+                Ok(statement) => {
+                    statements.push(statement);
+                }
+                Err(error) => {
+                    self.errors.push(error);
+                    self.recover(open_nodes);
+                }
+            }
+            self.skip_if_stuck(position);
+        }
+        self.depth -= 1;
+
+        self.finish_node();
+        let statements = self.sink.stmt_list(&statements);
+        return Ok(self.sink.stmt(StmtNode::Block(statements)));
+    }
+
+    // consumes the next token if it is of `kind`, otherwise reports it as missing
+    // and leaves the token in place. `context` describes what is being parsed
+    fn expect(&mut self, kind: TokenKind, context: &'static str) -> Option<&'a Token> {
+        if self.at(kind.clone()) {
+            return self.tokenizer.next();
+        }
+
+        let token = self.current();
+        self.error(ParseError::unexpected(token, vec![Expected::Token(kind)], context));
+        return None;
+    }
+
+    // a missing name comes back empty, a token that can't be a name but doesn't
+    // start something else either is skipped so the rest of the construct lines up
+    fn expect_identifier(&mut self, context: &'static str) -> (&'a str, Span) {
+        let token = self.current();
+        match &token.kind {
+            TokenKind::IDENTIFIER(name) => {
+                self.tokenizer.next();
+                return (name, token.span());
+            }
+            _ => {
+                self.error(ParseError::unexpected(token, vec![Expected::Identifier], context));
+                if !self.at_recovery_point() {
+                    self.skip_token();
+                }
+                return ("", token.span());
+            }
+        }
+    }
+
+    // a type is either one of the builtin type keywords or the name of a struct
+    fn parse_type_name(&mut self, context: &'static str) -> TypeAnnotationNode {
+        self.start_node(SyntaxKind::TYPE);
+        let token = self.current();
+        let name = match &token.kind {
+            TokenKind::KEYWORD(keyword) if ["int", "float", "string", "bool", "void"].contains(&keyword.as_str()) => {
+                self.tokenizer.next();
+                keyword.as_str()
+            }
+            TokenKind::IDENTIFIER(name) => {
+                self.tokenizer.next();
+                name.as_str()
+            }
+            _ => {
+                self.error(ParseError::unexpected(token, vec![Expected::Type], context));
+                if !self.at_recovery_point() {
+                    self.skip_token();
+                }
+                ""
+            }
+        };
+        self.finish_node();
+        return TypeAnnotationNode { name: self.sink.intern(name), span: token.span() };
+    }
+
+    // after an item of a comma separated list ended by `closing`, false once the
+    // list is over. a missing comma is only reported when another item follows,
+    // otherwise the missing closing delimiter is what gets reported
+    fn list_separator(&mut self, closing: TokenKind, context: &'static str) -> bool {
+        if self.at(TokenKind::COMMA) {
+            self.tokenizer.next();
+            return true;
+        }
+        if self.at_recovery_point() {
+            return false;
+        }
+        let token = self.current();
+        self.error(ParseError::unexpected(token, vec![Expected::Token(TokenKind::COMMA), Expected::Token(closing)], context));
+        return true;
+    }
+
+    // import "path/to/file.dod"; the path is resolved by the loader relative to the importing file
+    // imports are only allowed at the top of a file, the loader only looks there
+    fn parse_import(&mut self) -> Result<StmtId, ParseError> {
+        self.start_node(SyntaxKind::IMPORT_STMT);
+        // consume import
+        let keyword = self.tokenizer.next().unwrap();
+        if self.depth > 0 {
+            self.error(ParseError::NestedImport { span: keyword.span() });
+        }
+        let token = self.current();
+        let path = match &token.kind {
+            TokenKind::STRING(path) => self.sink.intern(path),
+            _ => {
+                return Err(ParseError::unexpected(token, vec![Expected::ImportPath], "import statement"));
+            }
+        };
+        self.tokenizer.next();
+        self.consume_semicolon();
+        self.finish_node();
+        return Ok(self.sink.stmt(StmtNode::IMPORT(path, token.span())));
+    }
+
+    // match (value) { 1 => a, "b" => { ... }, name => c, _ => d }
+    fn parse_match(&mut self) -> Result<StmtId, ParseError> {
+        self.start_node(SyntaxKind::MATCH_STMT);
+        // consume match
+        self.tokenizer.next().unwrap();
+        self.expect(TokenKind::LPAREN, "match statement");
+        let scrutinee = self.parse_expr()?;
+        self.expect(TokenKind::RPAREN, "match statement");
+        let opening = match self.expect(TokenKind::LCURLY, "match statement") {
+            Some(opening) => opening,
+            None => {
+                self.finish_node();
+                let arms = self.sink.match_arms(&[]);
+                return Ok(self.sink.stmt(StmtNode::MATCH(scrutinee, arms)));
+            }
+        };
+
+        let mut arms = Vec::new();
+        let mut covered = Vec::new();
+        loop {
+            if self.at(TokenKind::RCURLY) || self.at(TokenKind::EOF) {
+                break;
+            }
+
+            let position = self.tokenizer.position;
+            self.start_node(SyntaxKind::MATCH_ARM);
+            let span = self.current().span();
+            let (pattern, coverage) = self.parse_pattern();
+            self.check_duplicate_arm(&covered, &coverage, span);
+            covered.push((coverage, span));
+            self.expect(TokenKind::FATARROW, "match arm");
+
+            // a block body doesn't need a comma after it, an expression does unless it is the last arm
+            let is_block = self.at(TokenKind::LCURLY);
+            let body = if is_block {
+                self.parse_block()?
+            } else {
+                let start = self.tokenizer.position;
+                let expr = self.parse_expr()?;
+                let span = self.span_from(start);
+                self.sink.stmt(StmtNode::EXPR(expr, span))
+            };
+            arms.push(MatchArmNode { pattern, body });
+            self.finish_node();
+
+            if self.at(TokenKind::COMMA) {
+                self.tokenizer.next();
+            } else if !is_block && !self.at_recovery_point() {
+                let token = self.current();
+                self.error(ParseError::unexpected(
+                    token,
+                    vec![Expected::Token(TokenKind::COMMA), Expected::Token(TokenKind::RCURLY)],
+                    "match arm"
+                ));
+            }
+            self.skip_if_stuck(position);
+        }
+
+        self.consume_closing(TokenKind::RCURLY, opening);
+        self.finish_node();
+        let arms = self.sink.match_arms(&arms);
+        return Ok(self.sink.stmt(StmtNode::MATCH(scrutinee, arms)));
+    }
+
+    // a token that can't be a pattern becomes an error literal, which never
+    // counts as a duplicate of another arm
+    fn parse_pattern(&mut self) -> (PatternNode, Coverage<'a>) {
+        self.start_node(SyntaxKind::PATTERN);
+        let token = self.current();
+        let literal = match &token.kind {
+            TokenKind::INT(Some(a)) => ExprNode::INTEGERLITERAL(*a, token.span()),
+            TokenKind::FLOAT(a) => ExprNode::FLOATLITERAL(*a, token.span()),
+            TokenKind::STRING(a) => ExprNode::STRINGLITERAL(self.sink.intern(a), token.span()),
+            TokenKind::KEYWORD(keyword) if keyword == "true" || keyword == "false" => {
+                ExprNode::BOOLLITERAL(keyword == "true", token.span())
+            }
+            TokenKind::INT(None) => {
+                self.error(ParseError::IntegerTooLarge { span: token.span() });
+                self.tokenizer.next();
+                self.finish_node();
+                let error = self.sink.expr(ExprNode::Error);
+                return (PatternNode::Literal(error, token.span()), Coverage::Nothing);
+            }
+            TokenKind::IDENTIFIER(name) => {
+                self.tokenizer.next();
+                self.finish_node();
+                if name == "_" {
+                    return (PatternNode::Wildcard(token.span()), Coverage::Everything);
+                }
+                return (PatternNode::Binding(self.sink.intern(name), token.span()), Coverage::Everything);
             }
             _ => {
-                return Err(Diagnostic {
-                    range: Range {
-                        start: Position {line: identifier.line, character: identifier.column},
-                        end:  Position {line: identifier.line, character: identifier.column + 1},
-                    },
-                    severity: 1,
-                    message:format_args!("unimplemented keyword, '{:?}'", identifier.kind).to_string() 
-                })
+                self.error(ParseError::unexpected(token, vec![Expected::Pattern], "match arm"));
+                if !self.at_recovery_point() {
+                    self.skip_token();
+                }
+                self.finish_node();
+                let error = self.sink.expr(ExprNode::Error);
+                return (PatternNode::Literal(error, token.span()), Coverage::Nothing);
             }
         };
+        self.tokenizer.next();
+        self.finish_node();
+        let literal = self.sink.expr(literal);
+        return (PatternNode::Literal(literal, token.span()), Coverage::Literal(&token.kind));
+    }
 
-        // consume equal character
+    // an arm can never be reached if an earlier arm has the same literal or
+    // if an earlier arm matches everything (wildcard or binding)
+    fn check_duplicate_arm(&mut self, arms: &[(Coverage<'a>, Span)], coverage: &Coverage<'a>, span: Span) {
+        for (earlier, previous) in arms {
+            let covered = match (earlier, coverage) {
+                (Coverage::Everything, _) => true,
+                (Coverage::Literal(a), Coverage::Literal(b)) => a == b,
+                _ => false
+            };
+
+            if covered {
+                self.error(ParseError::UnreachableMatchArm { previous: *previous, span });
+                return;
+            }
+        }
+    }
+
+    // struct Point { x: float, y: float }
+    fn parse_struct_declaration(&mut self) -> Result<StmtId, ParseError> {
+        self.start_node(SyntaxKind::STRUCT_DECL);
+        // consume struct
         self.tokenizer.next().unwrap();
-        // parse expression
-        let expression = self.parse_expr()?;
-        // consume semicolon
+        let (name, _) = self.expect_identifier("struct declaration");
+        let name = self.sink.intern(name);
+        let opening = match self.expect(TokenKind::LCURLY, "struct declaration") {
+            Some(opening) => opening,
+            None => {
+                self.finish_node();
+                let fields = self.sink.field_declarations(&[]);
+                return Ok(self.sink.stmt(StmtNode::StructDeclaration(name, fields)));
+            }
+        };
+
+        let mut fields = Vec::new();
+        loop {
+            if self.at(TokenKind::RCURLY) || self.at(TokenKind::EOF) {
+                break;
+            }
+
+            let position = self.tokenizer.position;
+            self.start_node(SyntaxKind::FIELD_DECL);
+            let (field, field_span) = self.expect_identifier("struct field");
+            let field = self.sink.intern(field);
+            self.expect(TokenKind::COLON, "struct field");
+            let type_name = self.parse_type_name("struct field");
+            self.finish_node();
+            fields.push(FieldDeclarationNode { name: field, type_name: type_name.name, span: field_span.to(type_name.span) });
+
+            if !self.list_separator(TokenKind::RCURLY, "struct declaration") {
+                break;
+            }
+            self.skip_if_stuck(position);
+        }
+
+        self.consume_closing(TokenKind::RCURLY, opening);
+        self.finish_node();
+        let fields = self.sink.field_declarations(&fields);
+        return Ok(self.sink.stmt(StmtNode::StructDeclaration(name, fields)));
+    }
+
+    // function distance(a: Point, b: Point): float { ... }
+    fn parse_function_declaration(&mut self) -> Result<StmtId, ParseError> {
+        self.start_node(SyntaxKind::FUNCTION_DECL);
+        let start = self.tokenizer.position;
+        // consume function
         self.tokenizer.next().unwrap();
-        return Ok(Stmt::VariableDeclaration(identifier.to_string(), expression));
+        let (name, span) = self.expect_identifier("function declaration");
+        let name = self.sink.intern(name);
+        let parameters = self.parse_parameters();
+        let return_type = if self.at(TokenKind::COLON) {
+            self.tokenizer.next();
+            Some(self.parse_type_name("function declaration"))
+        } else {
+            None
+        };
+        let signature = self.span_from(start);
+        let body = self.parse_block()?;
+        self.finish_node();
+        Ok(self.sink.stmt(StmtNode::FunctionDeclaration(FunctionDeclarationNode {
+            name,
+            span,
+            signature,
+            parameters,
+            return_type,
+            body,
+        })))
     }
 
-    fn parse_expr(&mut self) -> Result<Expr, Diagnostic>{
-        let expr = self.parse_comparison_expr()?;
+    // (a: int, b: float), every parameter needs a type
+    fn parse_parameters(&mut self) -> ListRange {
+        self.start_node(SyntaxKind::PARAM_LIST);
+        let mut parameters = Vec::new();
+        let opening = match self.expect(TokenKind::LPAREN, "function declaration") {
+            Some(opening) => opening,
+            None => {
+                self.finish_node();
+                return self.sink.parameters(&parameters);
+            }
+        };
+
+        loop {
+            if self.at(TokenKind::RPAREN) || self.at(TokenKind::EOF) {
+                break;
+            }
+
+            let position = self.tokenizer.position;
+            self.start_node(SyntaxKind::PARAM);
+            let (name, span) = self.expect_identifier("parameter");
+            let name = self.sink.intern(name);
+            self.expect(TokenKind::COLON, "parameter");
+            let annotation = self.parse_type_name("parameter");
+            self.finish_node();
+            parameters.push(ParameterNode { name, span, annotation });
+
+            if !self.list_separator(TokenKind::RPAREN, "parameter list") {
+                break;
+            }
+            self.skip_if_stuck(position);
+        }
+
+        self.consume_closing(TokenKind::RPAREN, opening);
+        self.finish_node();
+        self.sink.parameters(&parameters)
+    }
+
+    // Point { x: 1.0, y: 2.0 }, the name has already been consumed
+    fn parse_struct_literal(&mut self, name: &str) -> Result<ExprId, ParseError> {
+        let name = self.sink.intern(name);
+        let opening = self.tokenizer.next().unwrap();
+
+        let mut fields = Vec::new();
+        loop {
+            if self.at(TokenKind::RCURLY) || self.at(TokenKind::EOF) {
+                break;
+            }
+
+            let position = self.tokenizer.position;
+            self.start_node(SyntaxKind::FIELD_INIT);
+            let (field, span) = self.expect_identifier("struct literal");
+            let field = self.sink.intern(field);
+            self.expect(TokenKind::COLON, "struct literal");
+            let value = self.parse_expr()?;
+            self.finish_node();
+            fields.push(FieldInitializerNode { name: field, value, span });
+
+            if !self.list_separator(TokenKind::RCURLY, "struct literal") {
+                break;
+            }
+            self.skip_if_stuck(position);
+        }
+
+        self.consume_closing(TokenKind::RCURLY, opening);
+        let fields = self.sink.field_initializers(&fields);
+        return Ok(self.sink.expr(ExprNode::STRUCTLITERAL(name, fields)));
+    }
+
+    fn parse_variable_declaration(&mut self) -> Result<StmtId, ParseError> {
+        self.start_node(SyntaxKind::LET_STMT);
+        // consume let or const
+        let keyword = self.tokenizer.next().unwrap();
+        let constant = keyword.kind == TokenKind::KEYWORD(String::from("const"));
+        let context = if constant { "constant declaration" } else { "variable declaration" };
+        let (identifier, span) = self.expect_identifier(context);
+        let name = self.sink.intern(identifier);
+        let annotation = if self.at(TokenKind::COLON) {
+            self.tokenizer.next();
+            Some(self.parse_type_name(context))
+        } else {
+            None
+        };
+
+        let initializer = if !constant && annotation.is_some() && self.at(TokenKind::SEMICOLON) {
+            None
+        } else {
+            // `let x;` is reported once for the missing '=' rather than again for the missing expression
+            let assign = if constant && !self.at(TokenKind::ASSIGNE) && self.at_recovery_point() {
+                self.error(ParseError::ConstantWithoutInitializer { name: identifier.to_string(), span });
+                None
+            } else {
+                self.expect(TokenKind::ASSIGNE, context)
+            };
+            if assign.is_some() || !self.at_recovery_point() {
+                Some(self.parse_expr()?)
+            } else {
+                Some(self.missing_expr())
+            }
+        };
+
+        self.consume_semicolon();
+        self.finish_node();
+        return Ok(self.sink.stmt(StmtNode::VariableDeclaration(VariableDeclarationNode {
+            name,
+            span,
+            constant,
+            annotation,
+            initializer,
+        })));
+    }
+
+    fn consume_semicolon(&mut self) {
+        self.expect(TokenKind::SEMICOLON, "statement");
+    }
+
+    fn parse_expr(&mut self) -> Result<ExprId, ParseError>{
+        let expr = self.parse_assignment_expr()?;
         return Ok(expr);
     }
 
-    fn parse_comparison_expr(&mut self) -> Result<Expr, Diagnostic>{
+    // assignment is right associative and only accepts identifiers, index and
+    // member expressions on its left side, e.g. `a = b = 1` or `a[i] = v`
+    fn parse_assignment_expr(&mut self) -> Result<ExprId, ParseError> {
+        let checkpoint = self.checkpoint();
+        let left = self.parse_conditional_expr()?;
+
+        if !self.at(TokenKind::ASSIGNE) {
+            return Ok(left);
+        }
+
+        // the left side is the node that starts at the checkpoint
+        let target = self.tokenizer.events[checkpoint];
+        self.start_node_at(checkpoint, SyntaxKind::ASSIGN_EXPR);
+        let operator = self.tokenizer.next().unwrap();
+        match target {
+            Event::Start(SyntaxKind::NAME_REF | SyntaxKind::INDEX_EXPR | SyntaxKind::MEMBER_EXPR | SyntaxKind::ERROR) => {},
+            _ => {
+                self.error(ParseError::InvalidAssignmentTarget { span: operator.span() });
+            }
+        }
+        let right = self.parse_assignment_expr()?;
+        self.finish_node();
+        return Ok(self.sink.expr(ExprNode::ASSIGNMENT(left, right)));
+    }
+
+    // `condition ? then : otherwise`, right associative so `a ? b : c ? d : e`
+    // groups as `a ? b : (c ? d : e)`
+    fn parse_conditional_expr(&mut self) -> Result<ExprId, ParseError> {
+        let checkpoint = self.checkpoint();
+        let condition = self.parse_comparison_expr()?;
+
+        if !self.at(TokenKind::QUESTION) {
+            return Ok(condition);
+        }
+
+        self.start_node_at(checkpoint, SyntaxKind::CONDITIONAL_EXPR);
+        self.tokenizer.next();
+        let then = self.parse_expr()?;
+        self.expect(TokenKind::COLON, "conditional expression");
+        let otherwise = self.parse_conditional_expr()?;
+        self.finish_node();
+        return Ok(self.sink.expr(ExprNode::CONDITIONAL(condition, then, otherwise)));
+    }
+
+    // `left operator right` once both operands are parsed
+    fn binary_expr(&mut self, left: ExprId, right: ExprId, operator: &Token) -> ExprId {
+        // only called with the operator tokens of the binary levels below
+        let op = BinaryOp::from_token(&operator.kind).expect("binary expression without an operator");
+        return self.sink.expr(ExprNode::BINARYEXPR(left, right, op, operator.span()));
+    }
+
+    fn parse_comparison_expr(&mut self) -> Result<ExprId, ParseError>{
+        let checkpoint = self.checkpoint();
         let mut left = self.parse_additive_expr()?;
 
         loop {
-            let token = self.tokenizer.peek();
-            match token {
-                Some(token) => match token.kind {
-                    TokenKind::LT | TokenKind::LTEQ | TokenKind::GTEQ | TokenKind::GT | TokenKind::EQ | TokenKind::NEQ => {
-                        let operator = self.tokenizer.next().unwrap();
-                        let right = self.parse_additive_expr()?;
-                        left = Expr::BINARYEXPR(Box::new(left), Box::new(right), operator.clone());
-                    }
-                    _ => {
-                        return Ok(left);
-                    }
-                },
+            let token = self.current();
+            match token.kind {
+                TokenKind::LT | TokenKind::LTEQ | TokenKind::GTEQ | TokenKind::GT | TokenKind::EQ | TokenKind::NEQ => {
+                    self.start_node_at(checkpoint, SyntaxKind::BINARY_EXPR);
+                    let operator = self.tokenizer.next().unwrap();
+                    let right = self.parse_additive_expr()?;
+                    self.finish_node();
+                    left = self.binary_expr(left, right, operator);
+                }
                 _ => {
-                    return Err(Diagnostic {
-                        range: Range {
-                            start: Position {line: 0, character: 0},
-                            end:  Position {line: 0, character: 1},
-                        },
-                        severity: 1,
-                        message: "unexpected end of file".to_string() 
-                    });
-                },
+                    return Ok(left);
+                }
             }
         }
     }
 
-    fn parse_additive_expr(&mut self) -> Result<Expr, Diagnostic> {
+    fn parse_additive_expr(&mut self) -> Result<ExprId, ParseError> {
+        let checkpoint = self.checkpoint();
         let mut left = self.parse_mult_expr()?;
 
         loop {
-            let token = self.tokenizer.peek();
-            match token {
-                Some(token) => match token.kind {
-                    TokenKind::ADD | TokenKind::SUB => {
-                        let operator = self.tokenizer.next().unwrap();
-                        let right = self.parse_mult_expr()?;
-                        left = Expr::BINARYEXPR(Box::new(left), Box::new(right), operator.clone());
-                    }
-                    _ => {
-                        return Ok(left);
-                    }
-                },
+            let token = self.current();
+            match token.kind {
+                TokenKind::ADD | TokenKind::SUB => {
+                    self.start_node_at(checkpoint, SyntaxKind::BINARY_EXPR);
+                    let operator = self.tokenizer.next().unwrap();
+                    let right = self.parse_mult_expr()?;
+                    self.finish_node();
+                    left = self.binary_expr(left, right, operator);
+                }
                 _ => {
-                    return Err(Diagnostic {
-                        range: Range {
-                            start: Position {line: 0, character: 0},
-                            end:  Position {line: 0, character: 1},
-                        },
-                        severity: 1,
-                        message: "unexpected end of file".to_string() 
-                    });
+                    return Ok(left);
                 }
             }
         }
     }
 
 
-    fn parse_mult_expr(&mut self) -> Result<Expr, Diagnostic> {
+    fn parse_mult_expr(&mut self) -> Result<ExprId, ParseError> {
+        let checkpoint = self.checkpoint();
         let mut left = self.parse_unary_expr()?;
 
         loop {
-            let token = self.tokenizer.peek();
-            match token {
-                Some(token) => match token.kind {
-                    TokenKind::MUL | TokenKind::DIV | TokenKind::MOD => {
-                        let operator = self.tokenizer.next().unwrap();
-                        let right = self.parse_unary_expr()?;
-                        left = Expr::BINARYEXPR(Box::new(left), Box::new(right), operator.clone());
-                    }
-                    _ => {
-                        return Ok(left);
-                    }
-                },
+            let token = self.current();
+            match token.kind {
+                TokenKind::MUL | TokenKind::DIV | TokenKind::MOD => {
+                    self.start_node_at(checkpoint, SyntaxKind::BINARY_EXPR);
+                    let operator = self.tokenizer.next().unwrap();
+                    let right = self.parse_unary_expr()?;
+                    self.finish_node();
+                    left = self.binary_expr(left, right, operator);
+                }
                 _ => {
-
-                    return Err(Diagnostic {
-                        range: Range {
-                            start: Position {line: 0, character: 0},
-                            end:  Position {line: 0, character: 1},
-                        },
-                        severity: 1,
-                        message: "unexpected end of file".to_string() 
-                    });
-                },
+                    return Ok(left);
+                }
             }
         }
     }
 
 
-    fn parse_parenthesized_exp(&mut self) -> Result<Expr, Diagnostic>{
-        let token = self.tokenizer.peek();
-        let stmt = match token {
-            Some(token) => match token.kind {
-                TokenKind::LPAREN => {
+    fn parse_parenthesized_exp(&mut self) -> Result<ExprId, ParseError>{
+        if !self.at(TokenKind::LPAREN) {
+            let expr = self.parse_primary_expr()?;
+            return Ok(expr);
+        }
+
+        self.start_node(SyntaxKind::PAREN_EXPR);
+        let opening = self.tokenizer.next().unwrap();
+        let stmt = self.parse_expr()?;
+        let stmt = self.sink.expr(ExprNode::PARENTHESIZED(stmt));
+        self.consume_closing(TokenKind::RPAREN, opening);
+        self.finish_node();
+        return Ok(stmt);
+    }
+
+    // postfix operators bind tighter than any prefix or binary operator,
+    // so `!a[0]` negates the element and `a.b(c)[0]` applies left to right
+    fn parse_postfix_expr(&mut self) -> Result<ExprId, ParseError> {
+        let checkpoint = self.checkpoint();
+        let mut expr = self.parse_parenthesized_exp()?;
+
+        loop {
+            let token = self.current();
+            match token.kind {
+                TokenKind::LSQUARE => {
+                    self.start_node_at(checkpoint, SyntaxKind::INDEX_EXPR);
+                    let opening = self.tokenizer.next().unwrap();
+                    let index = self.parse_expr()?;
+                    self.consume_closing(TokenKind::RSQUARE, opening);
+                    self.finish_node();
+                    expr = self.sink.expr(ExprNode::INDEX(expr, index));
+                }
+                TokenKind::DOT => {
+                    self.start_node_at(checkpoint, SyntaxKind::MEMBER_EXPR);
                     self.tokenizer.next();
-                    let stmt = self.parse_comparison_expr()?;
-                    let stmt = Expr::PARENTHESIZED(Box::new(stmt));
-                    let _closing = self.tokenizer.next();
-                    stmt
-                },
+                    let (member, _) = self.expect_identifier("member access");
+                    self.finish_node();
+                    let member = self.sink.intern(member);
+                    expr = self.sink.expr(ExprNode::MEMBER(expr, member));
+                }
+                TokenKind::LPAREN => {
+                    self.start_node_at(checkpoint, SyntaxKind::CALL_EXPR);
+                    self.start_node(SyntaxKind::ARG_LIST);
+                    let opening = self.tokenizer.next().unwrap();
+                    let arguments = self.parse_expr_list(TokenKind::RPAREN, opening)?;
+                    self.finish_node();
+                    self.finish_node();
+                    expr = self.sink.expr(ExprNode::CALL(expr, arguments));
+                }
                 _ => {
-                    let expr = self.parse_primary_expr()?;
                     return Ok(expr);
                 }
-            },
-            None => { 
-
-                    return Err(Diagnostic {
-                        range: Range {
-                            start: Position {line: 0, character: 0},
-                            end:  Position {line: 0, character: 1},
-                        },
-                        severity: 1,
-                        message: "unexpected end of file".to_string() 
-                    });
             }
-        };
-        return Ok(stmt);
+        }
     }
 
+    // consumes the token closing a delimiter opened by `opening`, when it is
+    // missing the delimiter is treated as closed and the token is left in place
+    fn consume_closing(&mut self, closing: TokenKind, opening: &Token) {
+        if self.at(closing.clone()) {
+            self.tokenizer.next();
+            return;
+        }
 
-    fn parse_unary_expr(&mut self) -> Result<Expr, Diagnostic>{
-        let token = self.tokenizer.peek();
-        let stmt = match token {
-            Some(token) => match token.kind {
-                TokenKind::BANG => {
-                    self.tokenizer.next().unwrap();
-                    let operand = self.parse_parenthesized_exp()?;
-                    Expr::UNARY(Box::new(operand))
-                },
-                _ => {
-                    let expr = self.parse_parenthesized_exp()?;
-                    return Ok(expr);
-                }
-            },
-            None => {
+        let token = self.current();
+        self.error(ParseError::UnclosedDelimiter {
+            delimiter: closing,
+            opening: opening.span(),
+            found: token.kind.clone(),
+            span: token.span()
+        });
+    }
 
-                return Err(Diagnostic {
-                    range: Range {
-                        start: Position {line: 0, character: 0},
-                        end:  Position {line: 0, character: 1},
-                    },
-                    severity: 1,
-                    message: "unexpected end of file".to_string() 
-                });
+    // parses comma separated expressions up to and including the token closing
+    // `opening`, a trailing comma is allowed. used for array literals and call arguments
+    fn parse_expr_list(&mut self, closing: TokenKind, opening: &Token) -> Result<ListRange, ParseError> {
+        let mut elements = Vec::new();
+
+        loop {
+            if self.at(closing.clone()) || self.at(TokenKind::EOF) {
+                break;
             }
-        };
-        return Ok(stmt);
+
+            let position = self.tokenizer.position;
+            let element = self.parse_expr()?;
+            elements.push(element);
+
+            if !self.list_separator(closing.clone(), "list") {
+                break;
+            }
+            self.skip_if_stuck(position);
+        }
+
+        self.consume_closing(closing, opening);
+        return Ok(self.sink.expr_list(&elements));
     }
 
-    fn parse_primary_expr(&mut self) -> Result<Expr, Diagnostic>{
-        let token = self.tokenizer.peek();
+    fn parse_array_literal(&mut self) -> Result<ExprId, ParseError> {
+        self.start_node(SyntaxKind::ARRAY_EXPR);
+        let opening = self.tokenizer.next().unwrap();
+        let elements = self.parse_expr_list(TokenKind::RSQUARE, opening)?;
+        self.finish_node();
+        return Ok(self.sink.expr(ExprNode::ARRAY(elements)));
+    }
+
+    fn parse_unary_expr(&mut self) -> Result<ExprId, ParseError>{
+        if !self.at(TokenKind::BANG) {
+            let expr = self.parse_postfix_expr()?;
+            return Ok(expr);
+        }
 
-        let stmt = match token {
-            Some(token) => match &token.kind {
-                TokenKind::IDENTIFIER(a) => {
+        self.start_node(SyntaxKind::UNARY_EXPR);
+        self.tokenizer.next().unwrap();
+        let operand = self.parse_postfix_expr()?;
+        self.finish_node();
+        return Ok(self.sink.expr(ExprNode::UNARY(operand)));
+    }
+
+    fn parse_primary_expr(&mut self) -> Result<ExprId, ParseError>{
+        let token = self.current();
+
+        let stmt = match &token.kind {
+            TokenKind::IDENTIFIER(a) => {
+                let checkpoint = self.checkpoint();
+                self.tokenizer.next();
+                let mut segments = vec![a.as_str()];
+                // `module::name` paths, each segment must be an identifier
+                while self.at(TokenKind::DCOLON) {
                     self.tokenizer.next();
-                    Expr::IDENTIFIER(a.to_string())
+                    let (segment, _) = self.expect_identifier("path");
+                    segments.push(segment);
                 }
-                TokenKind::INT(a) => {
-                    self.tokenizer.next();
-                    Expr::INTEGERLITERAL(*a)
+
+                if self.at(TokenKind::LCURLY) && segments.len() == 1 {
+                    self.start_node_at(checkpoint, SyntaxKind::STRUCT_LITERAL);
+                    let literal = self.parse_struct_literal(a)?;
+                    self.finish_node();
+                    return Ok(literal);
+                } else if segments.len() == 1 {
+                    self.start_node_at(checkpoint, SyntaxKind::NAME_REF);
+                    self.finish_node();
+                    ExprNode::IDENTIFIER(self.sink.intern(a), token.span())
+                } else {
+                    self.start_node_at(checkpoint, SyntaxKind::PATH_EXPR);
+                    self.finish_node();
+                    let segments: Vec<Symbol> = segments.iter().map(|segment| self.sink.intern(segment)).collect();
+                    ExprNode::PATH(self.sink.symbol_list(&segments))
                 }
-                TokenKind::FLOAT(a) => {
+            }
+            TokenKind::INT(a) => {
+                self.start_node(SyntaxKind::LITERAL);
+                self.tokenizer.next();
+                self.finish_node();
+                match a {
+                    Some(a) => ExprNode::INTEGERLITERAL(*a, token.span()),
+                    // reported instead of being read as some other number
+                    None => {
+                        self.error(ParseError::IntegerTooLarge { span: token.span() });
+                        ExprNode::Error
+                    }
+                }
+            }
+            TokenKind::FLOAT(a) => {
+                self.start_node(SyntaxKind::LITERAL);
+                self.tokenizer.next();
+                self.finish_node();
+                ExprNode::FLOATLITERAL(*a, token.span())
+            }
+            TokenKind::STRING(a) => {
+                self.start_node(SyntaxKind::LITERAL);
+                self.tokenizer.next();
+                self.finish_node();
+                ExprNode::STRINGLITERAL(self.sink.intern(a), token.span())
+            }
+            TokenKind::KEYWORD(keyword) if keyword == "true" || keyword == "false" => {
+                self.start_node(SyntaxKind::LITERAL);
+                self.tokenizer.next();
+                self.finish_node();
+                ExprNode::BOOLLITERAL(keyword == "true", token.span())
+            }
+            TokenKind::LSQUARE => {
+                return self.parse_array_literal();
+            }
+            _ => {
+                // the expression is reported and replaced by an error node, the
+                // offending token is only consumed if it doesn't belong to what follows
+                self.error(ParseError::unexpected(token, vec![Expected::Expression], "expression"));
+                self.start_node(SyntaxKind::ERROR);
+                if !self.at_recovery_point() {
                     self.tokenizer.next();
-                    Expr::FLOATLITERAL(*a)
                 }
-                _ => {
-                    return Err(Diagnostic {
-                        range: Range {
-                            start: Position {line: 0, character: 0},
-                            end:  Position {line: 0, character: 1},
-                        },
-                        severity: 1,
-                        message: format_args!("unexpected token {:?} expected 'identifier | number | string'", token.kind).to_string() 
-                    });
-                },
-            },
-            None => {
-                return Err(Diagnostic {
-                    range: Range {
-                        start: Position {line: 0, character: 0},
-                        end:  Position {line: 0, character: 1},
-                    },
-                    severity: 1,
-                    message: "unexpected end of file".to_string() 
-                });
-            },
+                self.finish_node();
+                ExprNode::Error
+            }
         };
 
-        return Ok(stmt);
+        return Ok(self.sink.expr(stmt));
     }
 }
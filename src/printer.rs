@@ -0,0 +1,348 @@
+// turns the AST back into DOD source.
+//
+// the output is formatted the same way no matter how the input was: 4 space
+// indentation, one statement per line and a blank line around statements that
+// span several lines. parentheses in the source are not kept, they are added
+// back only where precedence or associativity needs them, so `(a + b) * c`
+// keeps them and `a + (b * c)` prints as `a + b * c`.
+
+use crate::parser::{Expr, MatchArm, Pattern, Stmt};
+use crate::tokenizer::TokenKind;
+
+// binding strength of each expression level, the same levels the parser descends through
+const ASSIGNMENT: u8 = 1;
+const CONDITIONAL: u8 = 2;
+const COMPARISON: u8 = 3;
+const ADDITIVE: u8 = 4;
+const MULTIPLICATIVE: u8 = 5;
+const UNARY: u8 = 6;
+const POSTFIX: u8 = 7;
+const PRIMARY: u8 = 8;
+
+pub fn print_program(program: &[Stmt]) -> String {
+    let mut printer = Printer { out: String::new(), indent: 0 };
+    printer.statements(program);
+    printer.out
+}
+
+pub fn print_stmt(stmt: &Stmt) -> String {
+    print_program(std::slice::from_ref(stmt))
+}
+
+pub fn print_expr(expr: &Expr) -> String {
+    let mut printer = Printer { out: String::new(), indent: 0 };
+    printer.expr(expr, ASSIGNMENT);
+    printer.out
+}
+
+struct Printer {
+    out: String,
+    indent: usize,
+}
+
+// statements printed over several lines get a blank line before and after them
+fn is_multiline(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::StructDeclaration(_, fields) => !fields.is_empty(),
+        Stmt::FunctionDeclaration(_) | Stmt::IFSTATEMENT(_, _) | Stmt::WHILE(_, _) | Stmt::MATCH(_, _) => true,
+        Stmt::Block(statements) => !statements.is_empty(),
+        _ => false,
+    }
+}
+
+fn operator(kind: &TokenKind) -> (&'static str, u8) {
+    match kind {
+        TokenKind::ADD => ("+", ADDITIVE),
+        TokenKind::SUB => ("-", ADDITIVE),
+        TokenKind::MUL => ("*", MULTIPLICATIVE),
+        TokenKind::DIV => ("/", MULTIPLICATIVE),
+        TokenKind::MOD => ("%", MULTIPLICATIVE),
+        TokenKind::EQ => ("==", COMPARISON),
+        TokenKind::NEQ => ("!=", COMPARISON),
+        TokenKind::LT => ("<", COMPARISON),
+        TokenKind::LTEQ => ("<=", COMPARISON),
+        TokenKind::GT => (">", COMPARISON),
+        TokenKind::GTEQ => (">=", COMPARISON),
+        _ => ("?", COMPARISON),
+    }
+}
+
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::ASSIGNMENT(_, _) => ASSIGNMENT,
+        Expr::CONDITIONAL(_, _, _) => CONDITIONAL,
+        Expr::BINARYEXPR(_, _, kind, _) => operator(kind).1,
+        Expr::UNARY(_) => UNARY,
+        Expr::INDEX(_, _) | Expr::MEMBER(_, _) | Expr::CALL(_, _) => POSTFIX,
+        Expr::PARENTHESIZED(inner) => precedence(inner),
+        // there are no negative literals, they are written as a subtraction
+        Expr::INTEGERLITERAL(value, _) if *value < 0 => ADDITIVE,
+        Expr::FLOATLITERAL(value, _) if *value < 0.0 => ADDITIVE,
+        _ => PRIMARY,
+    }
+}
+
+fn float(value: f32) -> String {
+    // `Display` never uses an exponent, which the tokenizer wouldn't read
+    let text = value.to_string();
+    match text.contains('.') {
+        true => text,
+        false => text + ".0",
+    }
+}
+
+impl Printer {
+    fn line(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        let statements: Vec<&Stmt> = statements.iter().filter(|stmt| !matches!(stmt, Stmt::EOF)).collect();
+        for (index, stmt) in statements.iter().enumerate() {
+            if index > 0 {
+                if is_multiline(stmt) || is_multiline(statements[index - 1]) {
+                    self.out.push('\n');
+                }
+                self.line();
+            }
+            self.stmt(stmt);
+        }
+        if self.indent == 0 && !statements.is_empty() {
+            self.out.push('\n');
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::EOF => {}
+            Stmt::VariableDeclaration(declaration) => {
+                self.out.push_str(if declaration.constant { "const " } else { "let " });
+                self.out.push_str(&declaration.name);
+                if let Some(annotation) = &declaration.annotation {
+                    self.out.push_str(": ");
+                    self.out.push_str(&annotation.name);
+                }
+                if let Some(initializer) = &declaration.initializer {
+                    self.out.push_str(" = ");
+                    self.expr(initializer, ASSIGNMENT);
+                }
+                self.out.push(';');
+            }
+            Stmt::StructDeclaration(name, fields) => {
+                self.out.push_str("struct ");
+                self.out.push_str(name);
+                self.out.push_str(" {");
+                if !fields.is_empty() {
+                    self.indent += 1;
+                    for field in fields {
+                        self.line();
+                        self.out.push_str(&format!("{}: {},", field.name, field.type_name));
+                    }
+                    self.indent -= 1;
+                    self.line();
+                }
+                self.out.push('}');
+            }
+            Stmt::FunctionDeclaration(function) => {
+                self.out.push_str("function ");
+                self.out.push_str(&function.name);
+                self.out.push('(');
+                for (index, parameter) in function.parameters.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.out.push_str(&format!("{}: {}", parameter.name, parameter.annotation.name));
+                }
+                self.out.push(')');
+                if let Some(return_type) = &function.return_type {
+                    self.out.push_str(": ");
+                    self.out.push_str(&return_type.name);
+                }
+                self.out.push(' ');
+                self.block(&function.body);
+            }
+            Stmt::IFSTATEMENT(condition, body) => {
+                self.out.push_str("if (");
+                self.expr(condition, ASSIGNMENT);
+                self.out.push_str(") ");
+                self.block(body);
+            }
+            Stmt::WHILE(condition, body) => {
+                self.out.push_str("while (");
+                self.expr(condition, ASSIGNMENT);
+                self.out.push_str(") ");
+                self.block(body);
+            }
+            Stmt::RETURN(value, _) => {
+                self.out.push_str("return");
+                if let Some(value) = value {
+                    self.out.push(' ');
+                    self.expr(value, ASSIGNMENT);
+                }
+                self.out.push(';');
+            }
+            Stmt::BREAK(_) => self.out.push_str("break;"),
+            Stmt::CONTINUE(_) => self.out.push_str("continue;"),
+            Stmt::Block(_) => self.block(stmt),
+            Stmt::MATCH(scrutinee, arms) => {
+                self.out.push_str("match (");
+                self.expr(scrutinee, ASSIGNMENT);
+                self.out.push_str(") {");
+                if !arms.is_empty() {
+                    self.indent += 1;
+                    for arm in arms {
+                        self.line();
+                        self.match_arm(arm);
+                    }
+                    self.indent -= 1;
+                    self.line();
+                }
+                self.out.push('}');
+            }
+            Stmt::IMPORT(path, _) => {
+                self.out.push_str(&format!("import \"{}\";", path));
+            }
+            Stmt::EXPR(expr, _) => {
+                self.expr(expr, ASSIGNMENT);
+                self.out.push(';');
+            }
+        }
+    }
+
+    // bodies are always braced, a body that isn't a block becomes its only statement
+    fn block(&mut self, body: &Stmt) {
+        let statements = match body {
+            Stmt::Block(statements) => statements.as_slice(),
+            _ => std::slice::from_ref(body),
+        };
+        if statements.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push('{');
+        self.indent += 1;
+        self.line();
+        self.statements(statements);
+        self.indent -= 1;
+        self.line();
+        self.out.push('}');
+    }
+
+    fn match_arm(&mut self, arm: &MatchArm) {
+        match &arm.pattern {
+            Pattern::Literal(literal, _) => self.expr(literal, PRIMARY),
+            Pattern::Wildcard(_) => self.out.push('_'),
+            Pattern::Binding(name, _) => self.out.push_str(name),
+        }
+        self.out.push_str(" => ");
+        match &arm.body {
+            Stmt::EXPR(expr, _) => {
+                self.expr(expr, ASSIGNMENT);
+                self.out.push(',');
+            }
+            body => self.block(body),
+        }
+    }
+
+    // prints `expr` where an expression binding at least as tight as `min` is expected
+    fn expr(&mut self, expr: &Expr, min: u8) {
+        if precedence(expr) < min {
+            self.out.push('(');
+            self.expr(expr, ASSIGNMENT);
+            self.out.push(')');
+            return;
+        }
+
+        match expr {
+            Expr::INTEGERLITERAL(value, _) if *value < 0 => {
+                self.out.push_str(&format!("0 - {}", value.unsigned_abs()));
+            }
+            Expr::FLOATLITERAL(value, _) if *value < 0.0 => {
+                self.out.push_str(&format!("0.0 - {}", float(-value)));
+            }
+            Expr::INTEGERLITERAL(value, _) => self.out.push_str(&value.to_string()),
+            Expr::FLOATLITERAL(value, _) => self.out.push_str(&float(*value)),
+            Expr::STRINGLITERAL(value, _) => self.out.push_str(&format!("\"{}\"", value)),
+            Expr::BOOLLITERAL(value, _) => self.out.push_str(&value.to_string()),
+            Expr::IDENTIFIER(name, _) => self.out.push_str(name),
+            Expr::PATH(segments) => self.out.push_str(&segments.join("::")),
+            // `let x = ;` parses back into the same error
+            Expr::Error => {}
+            Expr::BINARYEXPR(left, right, kind, _) => {
+                // binary operators are left associative, a right operand on the same level needs parentheses
+                let (text, level) = operator(kind);
+                self.expr(left, level);
+                self.out.push_str(&format!(" {} ", text));
+                self.expr(right, level + 1);
+            }
+            Expr::PARENTHESIZED(inner) => self.expr(inner, min),
+            Expr::UNARY(operand) => {
+                self.out.push('!');
+                self.expr(operand, POSTFIX);
+            }
+            Expr::ARRAY(elements) => {
+                self.out.push('[');
+                self.list(elements);
+                self.out.push(']');
+            }
+            Expr::INDEX(base, index) => {
+                self.expr(base, POSTFIX);
+                self.out.push('[');
+                self.expr(index, ASSIGNMENT);
+                self.out.push(']');
+            }
+            Expr::ASSIGNMENT(target, value) => {
+                self.expr(target, CONDITIONAL);
+                self.out.push_str(" = ");
+                self.expr(value, ASSIGNMENT);
+            }
+            Expr::MEMBER(base, member) => {
+                self.expr(base, POSTFIX);
+                self.out.push('.');
+                self.out.push_str(member);
+            }
+            Expr::CALL(callee, arguments) => {
+                self.expr(callee, POSTFIX);
+                self.out.push('(');
+                self.list(arguments);
+                self.out.push(')');
+            }
+            Expr::STRUCTLITERAL(name, fields) => {
+                self.out.push_str(name);
+                if fields.is_empty() {
+                    self.out.push_str(" {}");
+                    return;
+                }
+                self.out.push_str(" { ");
+                for (index, field) in fields.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.out.push_str(&field.name);
+                    self.out.push_str(": ");
+                    self.expr(&field.value, ASSIGNMENT);
+                }
+                self.out.push_str(" }");
+            }
+            Expr::CONDITIONAL(condition, then, otherwise) => {
+                self.expr(condition, COMPARISON);
+                self.out.push_str(" ? ");
+                self.expr(then, ASSIGNMENT);
+                self.out.push_str(" : ");
+                self.expr(otherwise, CONDITIONAL);
+            }
+        }
+    }
+
+    fn list(&mut self, elements: &[Expr]) {
+        for (index, element) in elements.iter().enumerate() {
+            if index > 0 {
+                self.out.push_str(", ");
+            }
+            self.expr(element, ASSIGNMENT);
+        }
+    }
+}
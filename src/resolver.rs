@@ -0,0 +1,474 @@
+// name resolution.
+//
+// `resolve` walks a program with a stack of scopes: the file, every block,
+// every match arm and every function open one. the functions of a scope are
+// declared as soon as it is opened, so they can be called from anywhere in it,
+// themselves included. the parameters of a function share a scope with the
+// statements of its body. an identifier is looked up from the innermost scope
+// outwards and recorded against the declaration it names. a name that isn't
+// visible is reported as undefined, unless a scope that is open at the use
+// declares it further down, which is a use before declaration.
+//
+// declaring a name twice in one scope and assigning to a `const` are errors.
+// hiding a variable of an enclosing scope is only reported when
+// `Options::warn_shadowing` is set.
+//
+// the top level variables, constants and functions of an imported file are
+// declared in the file scope before anything else, against the import that
+// brings them in. `imported_names` collects them from the module graph.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+
+use crate::error::SemanticError;
+use crate::loader::{resolve_import, ModuleGraph};
+use crate::parser::{Expr, MatchArm, Pattern, Stmt};
+use crate::tokenizer::Span;
+use crate::visit::{self, Visitor};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScopeId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeclarationId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    File,
+    Block,
+    MatchArm,
+    Function,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    // in source order
+    pub declarations: Vec<DeclarationId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclarationKind {
+    Variable,
+    Constant,
+    MatchBinding,
+    Function,
+    Parameter,
+    // declared by an imported file, the span is the path of the import
+    Import,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub name: String,
+    pub kind: DeclarationKind,
+    // the span of the name
+    pub span: Span,
+    pub scope: ScopeId,
+}
+
+#[derive(Debug, Default)]
+pub struct Resolution {
+    // the file scope comes first, a scope always comes after its parent
+    pub scopes: Vec<Scope>,
+    pub declarations: Vec<Declaration>,
+    // the declaration every resolved identifier refers to, by the span of the identifier
+    pub references: HashMap<Span, DeclarationId>,
+    pub errors: Vec<SemanticError>,
+}
+
+impl Resolution {
+    pub fn declaration(&self, id: DeclarationId) -> &Declaration {
+        &self.declarations[id.0]
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.0]
+    }
+
+    // the declaration the identifier at `span` refers to
+    pub fn resolved(&self, span: Span) -> Option<&Declaration> {
+        self.references.get(&span).map(|id| self.declaration(*id))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    pub warn_shadowing: bool,
+}
+
+pub fn resolve(program: &[Stmt]) -> Resolution {
+    resolve_with(program, Options::default())
+}
+
+pub fn resolve_with(program: &[Stmt], options: Options) -> Resolution {
+    resolve_with_imports(program, &[], options)
+}
+
+// resolves a file whose imports make `imported` visible, each name with the
+// span of the import it comes from
+pub fn resolve_with_imports(program: &[Stmt], imported: &[(String, Span)], options: Options) -> Resolution {
+    let mut resolver = Resolver { resolution: Resolution::default(), frames: Vec::new(), options };
+    resolver.enter(ScopeKind::File, &[]);
+    for (name, span) in imported {
+        resolver.declare(name, DeclarationKind::Import, *span);
+    }
+    resolver.hoist(program);
+    resolver.visit_program(program);
+    resolver.exit();
+    resolver.resolution
+}
+
+// the names a file makes visible to the files importing it
+pub fn exports(program: &[Stmt]) -> Vec<String> {
+    let mut names = Vec::new();
+    for stmt in program {
+        match stmt {
+            Stmt::VariableDeclaration(declaration) => names.push(declaration.name.to_string()),
+            Stmt::FunctionDeclaration(function) => names.push(function.name.to_string()),
+            _ => {}
+        }
+    }
+    names
+}
+
+// the names the imports of `program`, the file at `path`, bring into scope.
+// imports the loader couldn't follow bring in nothing
+pub fn imported_names(graph: &ModuleGraph, path: &Path, program: &[Stmt]) -> Vec<(String, Span)> {
+    let mut imported = Vec::new();
+    for stmt in program {
+        let Stmt::IMPORT(import, span) = stmt else { continue };
+        if let Some(module) = graph.module(&resolve_import(path, import)) {
+            imported.extend(exports(&module.program).into_iter().map(|name| (name, *span)));
+        }
+    }
+    imported
+}
+
+// a scope while it is open
+struct Frame {
+    scope: ScopeId,
+    names: HashMap<String, DeclarationId>,
+    // variables the scope declares that haven't been reached yet, in order
+    upcoming: VecDeque<(String, Span)>,
+}
+
+struct Resolver {
+    resolution: Resolution,
+    frames: Vec<Frame>,
+    options: Options,
+}
+
+impl Resolver {
+    fn enter(&mut self, kind: ScopeKind, statements: &[Stmt]) {
+        let scope = ScopeId(self.resolution.scopes.len());
+        self.resolution.scopes.push(Scope {
+            kind,
+            parent: self.frames.last().map(|frame| frame.scope),
+            declarations: Vec::new(),
+        });
+        self.frames.push(Frame { scope, names: HashMap::new(), upcoming: VecDeque::new() });
+        self.hoist(statements);
+    }
+
+    // declares the functions among `statements` up front and remembers their
+    // variables as upcoming
+    fn hoist(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            match stmt {
+                Stmt::VariableDeclaration(declaration) => {
+                    let frame = self.frames.last_mut().unwrap();
+                    frame.upcoming.push_back((declaration.name.to_string(), declaration.span));
+                }
+                Stmt::FunctionDeclaration(function) => {
+                    self.declare(&function.name, DeclarationKind::Function, function.span);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn exit(&mut self) {
+        self.frames.pop();
+    }
+
+    fn declare(&mut self, name: &str, kind: DeclarationKind, span: Span) {
+        let frame = self.frames.last_mut().unwrap();
+        if frame.upcoming.front().map(|(_, upcoming)| *upcoming) == Some(span) {
+            frame.upcoming.pop_front();
+        }
+        // a name the parser couldn't read has nothing to refer to it
+        if name.is_empty() {
+            return;
+        }
+
+        if let Some(previous) = frame.names.get(name) {
+            let previous = self.resolution.declarations[previous.0].span;
+            self.resolution.errors.push(SemanticError::Redeclaration { name: name.to_string(), span, previous });
+        } else if self.options.warn_shadowing {
+            let outer = self.frames.iter().rev().skip(1).find_map(|frame| frame.names.get(name));
+            if let Some(shadowed) = outer {
+                let shadowed = self.resolution.declarations[shadowed.0].span;
+                self.resolution.errors.push(SemanticError::Shadowing { name: name.to_string(), span, shadowed });
+            }
+        }
+
+        let frame = self.frames.last_mut().unwrap();
+        let id = DeclarationId(self.resolution.declarations.len());
+        self.resolution.declarations.push(Declaration { name: name.to_string(), kind, span, scope: frame.scope });
+        self.resolution.scopes[frame.scope.0].declarations.push(id);
+        frame.names.insert(name.to_string(), id);
+    }
+
+    fn lookup(&mut self, name: &str, span: Span) {
+        for frame in self.frames.iter().rev() {
+            if let Some(id) = frame.names.get(name) {
+                self.resolution.references.insert(span, *id);
+                return;
+            }
+        }
+
+        let later = self
+            .frames
+            .iter()
+            .rev()
+            .find_map(|frame| frame.upcoming.iter().find(|(upcoming, _)| upcoming == name));
+        let error = match later {
+            Some((_, declaration)) => {
+                SemanticError::UseBeforeDeclaration { name: name.to_string(), span, declaration: *declaration }
+            }
+            None => SemanticError::UndefinedVariable { name: name.to_string(), span },
+        };
+        self.resolution.errors.push(error);
+    }
+}
+
+impl Visitor for Resolver {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VariableDeclaration(declaration) => {
+                // the initializer is resolved first, `let x = x;` can't see the new `x`
+                if let Some(initializer) = &declaration.initializer {
+                    self.visit_expr(initializer);
+                }
+                let kind = if declaration.constant { DeclarationKind::Constant } else { DeclarationKind::Variable };
+                self.declare(&declaration.name, kind, declaration.span);
+            }
+            // the function itself was declared when its scope was entered
+            Stmt::FunctionDeclaration(function) => {
+                let statements = match function.body.as_ref() {
+                    Stmt::Block(statements) => statements.as_slice(),
+                    body => std::slice::from_ref(body),
+                };
+                self.enter(ScopeKind::Function, &[]);
+                for parameter in &function.parameters {
+                    self.declare(&parameter.name, DeclarationKind::Parameter, parameter.span);
+                }
+                self.hoist(statements);
+                for stmt in statements {
+                    self.visit_stmt(stmt);
+                }
+                self.exit();
+            }
+            Stmt::Block(statements) => {
+                self.enter(ScopeKind::Block, statements);
+                visit::walk_stmt(self, stmt);
+                self.exit();
+            }
+            _ => visit::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_match_arm(&mut self, arm: &MatchArm) {
+        self.enter(ScopeKind::MatchArm, &[]);
+        visit::walk_match_arm(self, arm);
+        self.exit();
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        if let Pattern::Binding(name, span) = pattern {
+            self.declare(name, DeclarationKind::MatchBinding, *span);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::IDENTIFIER(name, span) = expr {
+            self.lookup(name, *span);
+        }
+        visit::walk_expr(self, expr);
+
+        if let Expr::ASSIGNMENT(target, _) = expr {
+            let Expr::IDENTIFIER(name, target_span) = target.as_ref() else { return };
+            let Some(declaration) = self.resolution.resolved(*target_span) else { return };
+            if declaration.kind == DeclarationKind::Constant {
+                self.resolution.errors.push(SemanticError::AssignmentToConstant {
+                    name: name.to_string(),
+                    span: expr.span().unwrap_or(*target_span),
+                    declaration: declaration.span,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::error::{ParseError, Severity};
+    use crate::loader::load_program_with;
+    use crate::tests::{reported, resolved, Resolved};
+    use crate::types::diagnostics::Diagnostic;
+
+    #[test]
+    fn test_resolver_reports_undefined_and_early_uses() {
+        let source = "let PI = 3.14;\nlet r = 5;\nif (r > 0) {\n    let area = r * r * PI;\n    print(areaa);\n}\nlet early = late;\nlet late = 1;\nlet y = y;\nmatch (r) { n => n, _ => n }";
+        let Resolved { resolution, .. } = resolved(source);
+
+        assert_eq!(
+            reported(&resolution.errors),
+            [
+                "5: E0201 undefined variable 'print'",
+                "5: E0201 undefined variable 'areaa'",
+                "7: E0202 'late' is used before its declaration on line 8",
+                "9: E0202 'y' is used before its declaration on line 9",
+                "10: E0201 undefined variable 'n'",
+            ]
+        );
+        match &resolution.errors[1] {
+            SemanticError::UndefinedVariable { span, .. } => assert_eq!((span.start_line, span.start_column, span.end_column), (5, 11, 15)),
+            other => panic!("expected an undefined variable, got {:?}", other),
+        }
+
+        // `r * r * PI` inside the if body refers to the file level declarations
+        let uses: Vec<(&str, u32)> = resolution
+            .references
+            .keys()
+            .filter(|span| span.start_line == 4)
+            .map(|span| {
+                let declaration = resolution.resolved(*span).unwrap();
+                (declaration.name.as_str(), declaration.span.start_line)
+            })
+            .collect();
+        assert_eq!(uses.len(), 3);
+        assert!(uses.iter().all(|(name, line)| (*name == "r" && *line == 2) || (*name == "PI" && *line == 1)));
+
+        let area = resolution.declarations.iter().find(|declaration| declaration.name == "area").unwrap();
+        assert_eq!(resolution.scope(area.scope).kind, ScopeKind::Block);
+        assert_eq!(resolution.scope(area.scope).parent.map(|parent| resolution.scope(parent).kind), Some(ScopeKind::File));
+        let binding = resolution.declarations.iter().find(|declaration| declaration.name == "n").unwrap();
+        assert_eq!(binding.kind, DeclarationKind::MatchBinding);
+        assert_eq!(resolution.scope(binding.scope).kind, ScopeKind::MatchArm);
+    }
+
+    #[test]
+    fn test_names_from_imported_files_resolve() {
+        let files: HashMap<PathBuf, &str> = HashMap::from([
+            (PathBuf::from("main.dod"), "import \"lib.dod\";\nlet b = limit + twice(1);\nhidden;\nlet limit = 2;"),
+            (PathBuf::from("lib.dod"), "import \"deep.dod\";\nconst limit = 10;\nfunction twice(n: int): int { return n * 2; }\n{ let hidden = 1; }"),
+            (PathBuf::from("deep.dod"), "let deep = 1;"),
+        ]);
+        let graph = load_program_with(Path::new("main.dod"), |path| {
+            files.get(path).map(|source| source.to_string()).ok_or(io::Error::from(io::ErrorKind::NotFound))
+        });
+        let main = graph.module(Path::new("main.dod")).unwrap();
+        let imported = imported_names(&graph, Path::new("main.dod"), &main.program);
+        let names: Vec<&str> = imported.iter().map(|(name, _)| name.as_str()).collect();
+        // only the top level of the imported file, not what it imports itself
+        assert_eq!(names, ["limit", "twice"]);
+
+        let resolution = resolve_with_imports(&main.program, &imported, Options::default());
+        assert_eq!(
+            reported(&resolution.errors),
+            ["3: E0201 undefined variable 'hidden'", "4: E0203 'limit' is already declared in this scope"]
+        );
+        let twice = resolution.declarations.iter().find(|declaration| declaration.name == "twice").unwrap();
+        assert_eq!(twice.kind, DeclarationKind::Import);
+        assert_eq!((twice.span.start_line, twice.span.start_column), (1, 8));
+    }
+
+    #[test]
+    fn test_redeclarations_and_opt_in_shadowing_point_at_the_original() {
+        let source = "let x = 1;\nlet x = 2;\nif (x) {\n    let x = 3;\n    let y = x;\n}\nmatch (x) { x => x }";
+        let Resolved { tree, resolution, .. } = resolved(source);
+
+        assert_eq!(reported(&resolution.errors), ["2: E0203 'x' is already declared in this scope"]);
+        assert!(matches!(&resolution.errors[0], SemanticError::Redeclaration { previous, .. } if previous.start_line == 1));
+
+        // the later declaration is the one that is used from then on
+        let condition = Span { start_line: 3, start_column: 5, end_line: 3, end_column: 5 };
+        assert_eq!(resolution.resolved(condition).unwrap().span.start_line, 2);
+
+        let errors = resolve_with(&tree.program, Options { warn_shadowing: true }).errors;
+        assert_eq!(
+            reported(&errors),
+            [
+                "2: E0203 'x' is already declared in this scope",
+                "4: W0203 'x' shadows the variable declared on line 2",
+                "7: W0203 'x' shadows the variable declared on line 2",
+            ]
+        );
+        assert_eq!(errors[1].severity(), Severity::Warning);
+
+        let diagnostic = Diagnostic::from(&errors[0]).in_document("file:///main.dod");
+        let json = serde_json::to_value(&diagnostic).unwrap();
+        assert_eq!(
+            json["relatedInformation"],
+            serde_json::json!([{
+                "location": {
+                    "uri": "file:///main.dod",
+                    "range": { "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 5 } }
+                },
+                "message": "'x' is first declared here"
+            }])
+        );
+    }
+
+    #[test]
+    fn test_constants_need_a_value_and_cannot_be_reassigned() {
+        let tree = crate::syntax::parse("const PI = 3.14;\nlet r = 1;\nr = 2;\nif (r) {\n    PI = r * 2;\n}\nconst E;");
+        assert!(matches!(&tree.program[0], Stmt::VariableDeclaration(declaration) if declaration.constant));
+        assert_eq!(tree.errors.len(), 1);
+        assert!(matches!(&tree.errors[0], ParseError::ConstantWithoutInitializer { name, span } if name == "E" && span.start_line == 7));
+        assert_eq!(tree.errors[0].code(), "E0006");
+
+        let resolution = resolve(&tree.program);
+        assert_eq!(resolution.declarations[0].kind, DeclarationKind::Constant);
+        assert_eq!(reported(&resolution.errors), ["5: E0204 cannot assign twice to constant 'PI'"]);
+        match &resolution.errors[0] {
+            SemanticError::AssignmentToConstant { span, declaration, .. } => {
+                assert_eq!((span.start_line, span.start_column, span.end_column), (5, 5, 14));
+                assert_eq!((declaration.start_line, declaration.start_column), (1, 7));
+            }
+            other => panic!("expected an assignment to a constant, got {:?}", other),
+        }
+
+        let diagnostic = Diagnostic::from(&resolution.errors[0]);
+        assert_eq!(diagnostic.related_information.len(), 1);
+        assert_eq!(diagnostic.related_information[0].location.range.start.line, 0);
+    }
+
+    #[test]
+    fn test_functions_can_be_called_before_they_are_declared() {
+        use crate::typecheck::check_types;
+
+        let source = "let n = twice(2);\nif (even(n)) {\n    helper();\n    function helper() {}\n}\nfunction twice(x: int): int {\n    return x * 2;\n}\nfunction even(x: int): bool {\n    return x == 0 ? true : odd(x - 1);\n}\nfunction odd(x: int): bool {\n    return x == 0 ? false : even(x - 1);\n}\ntwice(\"two\");\nhelper();\nlet early = later;\nlet later = 1;";
+        let Resolved { tree, resolution, .. } = resolved(source);
+
+        // calls further up and mutual recursion resolve, variables still have to come first
+        assert_eq!(
+            reported(&resolution.errors),
+            ["16: E0201 undefined variable 'helper'", "17: E0202 'later' is used before its declaration on line 18"]
+        );
+        let twice = resolution.resolved(Span { start_line: 1, start_column: 9, end_line: 1, end_column: 13 });
+        assert!(matches!(twice, Some(declaration) if declaration.kind == DeclarationKind::Function && declaration.span.start_line == 6));
+
+        // and their signatures are known at the call
+        let typing = check_types(&tree.program, &resolution);
+        assert_eq!(reported(&typing.errors), ["15: E0308 mismatched argument to 'twice', expected int found string"]);
+    }
+}
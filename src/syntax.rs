@@ -0,0 +1,501 @@
+// lossless concrete syntax tree.
+//
+// the parser records `Event`s as it goes, those events are turned into an
+// immutable green tree here. green nodes only know their kind, their children
+// and the length of their text, so they can be shared and rebuilt cheaply.
+// `SyntaxNode` and `SyntaxToken` (the red tree) wrap green elements with their
+// absolute offset and parent, which is what editor features navigate and what
+// the typed views in `ast` and the `Stmt`/`Expr` program are read off.
+//
+// every byte of the source ends up in exactly one token: whitespace and comments
+// become trivia tokens and anything the parser didn't consume ends up in `ERROR`
+// nodes, so `root.text() == source` always holds.
+
+use std::{ops::Range, rc::Rc};
+
+use crate::ast;
+use crate::parser::{Parser, Stmt};
+use crate::tokenizer::{Token, TokenKind, Tokenizer};
+use crate::error::ParseError;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    // tokens, mirroring `TokenKind`
+    SEMICOLON, COLON, DCOLON, LPAREN, RPAREN, LCURLY, RCURLY, LSQUARE, RSQUARE, COMMA, DOT,
+    ADD, SUB, MUL, DIV, MOD,
+    INT, FLOAT, STRING, IDENTIFIER, KEYWORD,
+    BANG, QUESTION, ASSIGNE, FATARROW,
+    EQ, GT, LT, GTEQ, LTEQ, NEQ,
+
+    // trivia
+    WHITESPACE, COMMENT,
+
+    // nodes
+    PROGRAM,
+    ERROR,
+    LET_STMT,
+    IF_STMT,
+    WHILE_STMT,
+    RETURN_STMT,
+    BREAK_STMT,
+    CONTINUE_STMT,
+    BLOCK,
+    STRUCT_DECL,
+    FIELD_DECL,
+    FUNCTION_DECL,
+    PARAM_LIST,
+    PARAM,
+    TYPE,
+    MATCH_STMT,
+    MATCH_ARM,
+    PATTERN,
+    IMPORT_STMT,
+    EXPR_STMT,
+    LITERAL,
+    NAME_REF,
+    PATH_EXPR,
+    BINARY_EXPR,
+    PAREN_EXPR,
+    UNARY_EXPR,
+    ARRAY_EXPR,
+    INDEX_EXPR,
+    ASSIGN_EXPR,
+    MEMBER_EXPR,
+    CALL_EXPR,
+    ARG_LIST,
+    STRUCT_LITERAL,
+    FIELD_INIT,
+    CONDITIONAL_EXPR,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, SyntaxKind::WHITESPACE | SyntaxKind::COMMENT)
+    }
+
+    pub fn from_token(kind: &TokenKind) -> SyntaxKind {
+        match kind {
+            TokenKind::SEMICOLON => SyntaxKind::SEMICOLON,
+            TokenKind::COLON => SyntaxKind::COLON,
+            TokenKind::DCOLON => SyntaxKind::DCOLON,
+            TokenKind::LPAREN => SyntaxKind::LPAREN,
+            TokenKind::RPAREN => SyntaxKind::RPAREN,
+            TokenKind::LCURLY => SyntaxKind::LCURLY,
+            TokenKind::RCURLY => SyntaxKind::RCURLY,
+            TokenKind::LSQUARE => SyntaxKind::LSQUARE,
+            TokenKind::RSQUARE => SyntaxKind::RSQUARE,
+            TokenKind::COMMA => SyntaxKind::COMMA,
+            TokenKind::DOT => SyntaxKind::DOT,
+            TokenKind::ADD => SyntaxKind::ADD,
+            TokenKind::SUB => SyntaxKind::SUB,
+            TokenKind::MUL => SyntaxKind::MUL,
+            TokenKind::DIV => SyntaxKind::DIV,
+            TokenKind::MOD => SyntaxKind::MOD,
+            TokenKind::INT(_) => SyntaxKind::INT,
+            TokenKind::FLOAT(_) => SyntaxKind::FLOAT,
+            TokenKind::STRING(_) => SyntaxKind::STRING,
+            TokenKind::IDENTIFIER(_) => SyntaxKind::IDENTIFIER,
+            TokenKind::KEYWORD(_) => SyntaxKind::KEYWORD,
+            TokenKind::BANG => SyntaxKind::BANG,
+            TokenKind::QUESTION => SyntaxKind::QUESTION,
+            TokenKind::ASSIGNE => SyntaxKind::ASSIGNE,
+            TokenKind::FATARROW => SyntaxKind::FATARROW,
+            TokenKind::EQ => SyntaxKind::EQ,
+            TokenKind::GT => SyntaxKind::GT,
+            TokenKind::LT => SyntaxKind::LT,
+            TokenKind::GTEQ => SyntaxKind::GTEQ,
+            TokenKind::LTEQ => SyntaxKind::LTEQ,
+            TokenKind::NEQ => SyntaxKind::NEQ,
+            // EOF has no text, it never makes it into the tree
+            TokenKind::EOF => SyntaxKind::ERROR,
+        }
+    }
+}
+
+// what the parser records while parsing, `Token` holds an index into the token list
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Start(SyntaxKind),
+    Token(usize),
+    Finish,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GreenToken {
+    pub kind: SyntaxKind,
+    pub text: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GreenNode {
+    pub kind: SyntaxKind,
+    pub width: usize,
+    pub children: Vec<GreenElement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            GreenElement::Node(node) => node.kind,
+            GreenElement::Token(token) => token.kind,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.width,
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> GreenNode {
+        let width = children.iter().map(|child| child.width()).sum();
+        GreenNode { kind, width, children }
+    }
+
+    fn write_text(&self, text: &mut String) {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => node.write_text(text),
+                GreenElement::Token(token) => text.push_str(&token.text),
+            }
+        }
+    }
+}
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+// a green node positioned in the file, cheap to clone
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+#[derive(Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> SyntaxNode {
+        SyntaxNode(Rc::new(NodeData { green, offset: 0, parent: None }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    // byte range of the node in the source, trivia inside the node included
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.width
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        self.0.green.write_text(&mut text);
+        text
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        let mut children = Vec::new();
+        for child in &self.0.green.children {
+            match child {
+                GreenElement::Node(node) => {
+                    children.push(SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                        green: node.clone(),
+                        offset,
+                        parent: Some(self.clone()),
+                    }))));
+                }
+                GreenElement::Token(token) => {
+                    children.push(SyntaxElement::Token(SyntaxToken {
+                        green: token.clone(),
+                        offset,
+                        parent: self.clone(),
+                    }));
+                }
+            }
+            offset += child.width();
+        }
+        children
+    }
+
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+
+    // every token in the subtree in source order, trivia included
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    // the token covering `offset`, at a boundary between two tokens the right one wins
+    pub fn token_at_offset(&self, offset: usize) -> Option<SyntaxToken> {
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => {
+                    if node.text_range().contains(&offset) {
+                        return node.token_at_offset(offset);
+                    }
+                }
+                SyntaxElement::Token(token) => {
+                    if token.text_range().contains(&offset) {
+                        return Some(token);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            SyntaxElement::Node(node) => node.kind(),
+            SyntaxElement::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        match self {
+            SyntaxElement::Node(node) => node.text_range(),
+            SyntaxElement::Token(token) => token.text_range(),
+        }
+    }
+}
+
+// the syntax tree of a file together with the AST read off it by `ast::lower`:
+// `program[i]` is the statement spelled by the i-th non error node under `root`
+pub struct SyntaxTree {
+    pub root: SyntaxNode,
+    pub program: Vec<Stmt>,
+    pub errors: Vec<ParseError>,
+}
+
+impl SyntaxTree {
+    pub fn statements(&self) -> Vec<SyntaxNode> {
+        self.root.children().into_iter().filter(|node| node.kind() != SyntaxKind::ERROR).collect()
+    }
+}
+
+pub fn parse(source: &str) -> SyntaxTree {
+    let tokens = Tokenizer::tokenize(source);
+    let mut parser = Parser::new(&tokens);
+    parser.parse();
+    let root = SyntaxNode::new_root(Rc::new(build_tree(source, &tokens, parser.events())));
+
+    SyntaxTree {
+        program: ast::lower(source, &tokens, &root),
+        root,
+        errors: std::mem::take(&mut parser.errors),
+    }
+}
+
+// byte offset of the first byte of every line
+fn line_starts(source: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    for (offset, byte) in source.bytes().enumerate() {
+        if byte == b'\n' {
+            starts.push(offset + 1);
+        }
+    }
+    starts
+}
+
+// byte range of a token, tokens count columns in bytes so this is exact
+fn token_range(token: &Token, starts: &[usize]) -> Range<usize> {
+    let start = starts[token.start_line as usize - 1] + token.start_column as usize - 1;
+    let end = starts[token.line as usize - 1] + token.column as usize;
+    start..end
+}
+
+struct Builder<'a> {
+    source: &'a str,
+    // text consumed so far
+    offset: usize,
+    stack: Vec<(SyntaxKind, Vec<GreenElement>)>,
+}
+
+impl<'a> Builder<'a> {
+    fn push(&mut self, kind: SyntaxKind, end: usize) {
+        let mut end = end.max(self.offset).min(self.source.len());
+        while !self.source.is_char_boundary(end) {
+            end += 1;
+        }
+        let text = self.source[self.offset..end].to_string();
+        self.offset = end;
+        let token = GreenElement::Token(Rc::new(GreenToken { kind, text }));
+        self.stack.last_mut().unwrap().1.push(token);
+    }
+
+    // splits the text up to `end` into whitespace, `//` comments and error tokens
+    fn trivia(&mut self, end: usize) {
+        while self.offset < end {
+            let rest = &self.source[self.offset..end];
+            let first = rest.as_bytes()[0];
+            if first.is_ascii_whitespace() {
+                let length = rest.bytes().take_while(|byte| byte.is_ascii_whitespace()).count();
+                self.push(SyntaxKind::WHITESPACE, self.offset + length);
+            } else if rest.starts_with("//") {
+                let length = rest.find('\n').unwrap_or(rest.len());
+                self.push(SyntaxKind::COMMENT, self.offset + length);
+            } else {
+                let mut length = 0;
+                for (index, c) in rest.char_indices() {
+                    if c.is_ascii_whitespace() || rest[index..].starts_with("//") {
+                        break;
+                    }
+                    length = index + c.len_utf8();
+                }
+                self.push(SyntaxKind::ERROR, self.offset + length);
+            }
+        }
+    }
+
+    fn start(&mut self, kind: SyntaxKind) {
+        self.stack.push((kind, Vec::new()));
+    }
+
+    fn finish(&mut self) {
+        let (kind, children) = self.stack.pop().unwrap();
+        let node = GreenElement::Node(Rc::new(GreenNode::new(kind, children)));
+        self.stack.last_mut().unwrap().1.push(node);
+    }
+}
+
+// byte range of every token in `source`, EOF sits at the very end
+pub(crate) fn token_ranges(source: &str, tokens: &[Token]) -> Vec<Range<usize>> {
+    let starts = line_starts(source);
+    tokens
+        .iter()
+        .map(|token| match token.kind {
+            TokenKind::EOF => source.len()..source.len(),
+            _ => token_range(token, &starts),
+        })
+        .collect()
+}
+
+pub fn build_tree(source: &str, tokens: &[Token], events: &[Event]) -> GreenNode {
+    let ranges = token_ranges(source, tokens);
+
+    let mut builder = Builder {
+        source,
+        offset: 0,
+        stack: vec![(SyntaxKind::PROGRAM, Vec::new())],
+    };
+    let mut next_token = 0;
+
+    for event in events {
+        match event {
+            Event::Start(kind) => {
+                // trivia in front of a node belongs to the parent
+                if let Some(range) = ranges.get(next_token) {
+                    builder.trivia(range.start);
+                }
+                builder.start(*kind);
+            }
+            Event::Token(index) => {
+                // tokens skipped by the parser while recovering are kept as errors
+                while next_token < *index {
+                    builder.trivia(ranges[next_token].start);
+                    builder.push(SyntaxKind::ERROR, ranges[next_token].end);
+                    next_token += 1;
+                }
+                next_token = index + 1;
+                if let TokenKind::EOF = tokens[*index].kind {
+                    continue;
+                }
+                builder.trivia(ranges[*index].start);
+                builder.push(SyntaxKind::from_token(&tokens[*index].kind), ranges[*index].end);
+            }
+            Event::Finish => {
+                if builder.stack.len() > 1 {
+                    builder.finish();
+                }
+            }
+        }
+    }
+
+    // nodes left open by a parse error are closed where the parser stopped
+    while builder.stack.len() > 1 {
+        builder.finish();
+    }
+
+    // whatever the parser never got to is wrapped in a single error node
+    let rest: Vec<usize> = (next_token..tokens.len())
+        .filter(|index| !matches!(tokens[*index].kind, TokenKind::EOF))
+        .collect();
+    if !rest.is_empty() {
+        builder.trivia(ranges[rest[0]].start);
+        builder.start(SyntaxKind::ERROR);
+        for index in rest {
+            builder.trivia(ranges[index].start);
+            builder.push(SyntaxKind::from_token(&tokens[index].kind), ranges[index].end);
+        }
+        builder.finish();
+    }
+    builder.trivia(source.len());
+
+    let (kind, children) = builder.stack.pop().unwrap();
+    GreenNode::new(kind, children)
+}
@@ -6,3 +6,667 @@
 //     assert_eq!("Content-Length: 16\r\n\r\n{\"testing\":true}", serialize_message(msg));
 // }
 
+
+use std::{collections::HashMap, io, path::{Path, PathBuf}};
+
+use crate::arena::Ast;
+use crate::error::{SemanticError, Severity};
+use crate::loader::{load_program_with, ImportError};
+use crate::parser::Stmt;
+use crate::resolver::{resolve, Resolution};
+use crate::syntax::SyntaxTree;
+use crate::tokenizer::Tokenizer;
+use crate::types::diagnostics::Diagnostic;
+
+// the program and diagnostics of parsing `source`
+fn parse(source: &str) -> (Vec<Stmt>, Vec<Diagnostic>) {
+    let tree = crate::syntax::parse(source);
+    (tree.program, tree.errors.iter().map(Diagnostic::from).collect())
+}
+
+// a program that parsed without errors and had its names resolved, which is
+// where the tests of the passes after the parser start from
+pub(crate) struct Resolved {
+    pub tree: SyntaxTree,
+    pub ast: Ast,
+    pub resolution: Resolution,
+}
+
+pub(crate) fn resolved(source: &str) -> Resolved {
+    let tree = crate::syntax::parse(source);
+    assert!(tree.errors.iter().all(|error| error.severity() != Severity::Error), "{:?}", tree.errors);
+    let ast = Ast::from_program(&tree.program);
+    let resolution = resolve(&tree.program);
+    Resolved { tree, ast, resolution }
+}
+
+// every error as `line: code message`, in the order they were reported
+pub(crate) fn reported(errors: &[SemanticError]) -> Vec<String> {
+    errors.iter().map(|error| format!("{}: {} {}", error.span().start_line, error.code(), error)).collect()
+}
+
+#[test]
+fn test_blocks_parse_anywhere_a_statement_can() {
+    use crate::parser::Expr;
+
+    let (program, diagnostics) = parse("{ let a = 1; { a } }\nif (a) { let b = a; }\n{ }");
+    assert!(diagnostics.is_empty());
+    assert_eq!(program.len(), 3);
+    match &program[0] {
+        Stmt::Block(statements) => {
+            assert!(matches!(&statements[0], Stmt::VariableDeclaration(declaration) if declaration.name == "a"));
+            assert!(matches!(&statements[1], Stmt::Block(inner) if matches!(&inner[0], Stmt::EXPR(Expr::IDENTIFIER(_, _), _))));
+        }
+        _ => panic!("expected a block"),
+    }
+    assert!(matches!(&program[1], Stmt::IFSTATEMENT(_, body) if matches!(&**body, Stmt::Block(statements) if statements.len() == 1)));
+    assert!(matches!(&program[2], Stmt::Block(statements) if statements.is_empty()));
+
+    // a block left open points back at its `{`
+    let (_, diagnostics) = parse("let x = 1;\n{ let a = 1;\n");
+    assert_eq!(diagnostics.len(), 1);
+    let range = &diagnostics[0].range;
+    assert_eq!((range.start.line, range.start.character), (1, 0));
+}
+
+#[test]
+fn test_arrays_index_and_assignment_parse() {
+    use crate::parser::{Expr, VariableDeclaration};
+
+    let (program, diagnostics) = parse("let a = [1, [2, 3], ];\na[0][1] = a[2] = 4;\nlet e = [];");
+    assert!(diagnostics.is_empty());
+    match &program[0] {
+        Stmt::VariableDeclaration(VariableDeclaration { initializer: Some(Expr::ARRAY(elements)), .. }) => {
+            assert_eq!(elements.len(), 2);
+            assert!(matches!(&elements[1], Expr::ARRAY(inner) if inner.len() == 2));
+        }
+        _ => panic!("expected an array"),
+    }
+    // indexing is left associative, assignment right associative
+    match &program[1] {
+        Stmt::EXPR(Expr::ASSIGNMENT(target, value), _) => {
+            assert!(matches!(&**target, Expr::INDEX(base, index)
+                if matches!(&**base, Expr::INDEX(_, _)) && matches!(**index, Expr::INTEGERLITERAL(1, _))));
+            assert!(matches!(&**value, Expr::ASSIGNMENT(_, _)));
+        }
+        _ => panic!("expected an assignment"),
+    }
+    assert!(matches!(&program[2], Stmt::VariableDeclaration(declaration)
+        if matches!(&declaration.initializer, Some(Expr::ARRAY(elements)) if elements.is_empty())));
+
+    // the `;` after an expression statement stays optional
+    let (program, diagnostics) = parse("a\nb = 1;\nb[0]");
+    assert!(diagnostics.is_empty());
+    assert_eq!(program.len(), 3);
+    assert!(matches!(&program[2], Stmt::EXPR(Expr::INDEX(_, _), _)));
+
+    // only places can be assigned to
+    let (_, diagnostics) = parse("[a] = 1;");
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].message.starts_with("invalid assignment target"));
+
+    // an unclosed `[` is reported where the `]` is missing
+    let (_, diagnostics) = parse("let b = 1;\nlet c = b[0;");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "unclosed delimiter, expected ']' found ';'");
+    assert_eq!((diagnostics[0].range.start.line, diagnostics[0].range.start.character), (1, 11));
+}
+
+#[test]
+fn test_members_calls_and_paths_parse_as_postfix_expressions() {
+    use crate::parser::Expr;
+
+    let (program, diagnostics) = parse("a.b.c(1, d)[0];\nmath::max(x, y);\n!p.q;");
+    assert!(diagnostics.is_empty());
+    // postfix operators apply left to right
+    match &program[0] {
+        Stmt::EXPR(Expr::INDEX(call, _), _) => match &**call {
+            Expr::CALL(callee, arguments) => {
+                assert_eq!(arguments.len(), 2);
+                assert!(matches!(&**callee, Expr::MEMBER(base, name)
+                    if name == "c" && matches!(&**base, Expr::MEMBER(_, name) if name == "b")));
+            }
+            _ => panic!("expected a call"),
+        },
+        _ => panic!("expected an index expression"),
+    }
+    assert!(matches!(&program[1], Stmt::EXPR(Expr::CALL(callee, _), _)
+        if matches!(&**callee, Expr::PATH(segments) if segments == &["math", "max"])));
+    // and bind tighter than `!`
+    assert!(matches!(&program[2], Stmt::EXPR(Expr::UNARY(operand), _) if matches!(**operand, Expr::MEMBER(_, _))));
+
+    // members and path segments have to be names
+    for source in ["a.1;", "math::;"] {
+        let (_, diagnostics) = parse(source);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("expected identifier"), "{}", diagnostics[0].message);
+    }
+}
+
+#[test]
+fn test_token_columns_are_one_based_on_every_line() {
+    let tokens = Tokenizer::tokenize("let ab = 12;\n  \"s\" >= x\n");
+    let columns: Vec<(u32, u32, u32, u32)> = tokens
+        .iter()
+        .map(|token| (token.start_line, token.start_column, token.line, token.column))
+        .collect();
+    assert_eq!(
+        columns,
+        [
+            // the first line counts from 1 like the others
+            (1, 1, 1, 3),
+            (1, 5, 1, 6),
+            (1, 8, 1, 8),
+            (1, 10, 1, 11),
+            (1, 12, 1, 12),
+            (2, 3, 2, 5),
+            (2, 7, 2, 8),
+            (2, 10, 2, 10),
+            // EOF sits on the empty last line
+            (3, 1, 3, 1),
+        ]
+    );
+
+    // or just past the last byte when there is no newline at the end
+    let tokens = Tokenizer::tokenize("a;");
+    let eof = tokens.last().unwrap();
+    assert_eq!((eof.start_line, eof.start_column, eof.line, eof.column), (1, 3, 1, 3));
+}
+
+#[test]
+fn test_structs_and_struct_literals_parse_with_field_spans() {
+    use crate::parser::{Expr, VariableDeclaration};
+
+    let (program, diagnostics) = parse("struct Point { x: float, y: float, }\nlet p = Point { x: 1.0, y: a + 2.0 };");
+    assert!(diagnostics.is_empty());
+    match &program[0] {
+        Stmt::StructDeclaration(name, fields) => {
+            assert_eq!(name, "Point");
+            let names: Vec<(&str, &str)> = fields.iter().map(|field| (field.name.as_str(), field.type_name.as_str())).collect();
+            assert_eq!(names, [("x", "float"), ("y", "float")]);
+            // a field declaration covers `name: type`, a field initializer only the name
+            assert_eq!((fields[1].span.start_column, fields[1].span.end_column), (26, 33));
+        }
+        _ => panic!("expected a struct declaration"),
+    }
+    match &program[1] {
+        Stmt::VariableDeclaration(VariableDeclaration { initializer: Some(Expr::STRUCTLITERAL(name, fields)), .. }) => {
+            assert_eq!(name, "Point");
+            assert_eq!(fields.len(), 2);
+            assert_eq!((fields[1].name.as_str(), fields[1].span.start_column, fields[1].span.end_column), ("y", 25, 25));
+            assert!(matches!(fields[1].value, Expr::BINARYEXPR(_, _, _, _)));
+        }
+        _ => panic!("expected a struct literal"),
+    }
+
+    // a field without its `:` is reported at the token found instead
+    let (_, diagnostics) = parse("struct P { x float, y: int }");
+    assert_eq!(diagnostics.len(), 1);
+    let range = &diagnostics[0].range;
+    assert_eq!((range.start.line, range.start.character), (0, 13));
+}
+
+#[test]
+fn test_conditional_expressions_parse_right_associative() {
+    use crate::parser::Expr;
+
+    let (program, diagnostics) = parse("let scale = big ? 0.5 : 1.0;\na < b ? c : d ? e : f;\nx = y ? 1 : 2;");
+    assert!(diagnostics.is_empty());
+    assert!(matches!(&program[0], Stmt::VariableDeclaration(declaration)
+        if matches!(declaration.initializer, Some(Expr::CONDITIONAL(_, _, _)))));
+    // the condition is a whole comparison and the `else` side nests to the right
+    match &program[1] {
+        Stmt::EXPR(Expr::CONDITIONAL(condition, then, otherwise), _) => {
+            assert!(matches!(**condition, Expr::BINARYEXPR(_, _, _, _)));
+            assert!(matches!(&**then, Expr::IDENTIFIER(name, _) if name == "c"));
+            assert!(matches!(**otherwise, Expr::CONDITIONAL(_, _, _)));
+        }
+        _ => panic!("expected a conditional"),
+    }
+    // assignment binds looser
+    assert!(matches!(&program[2], Stmt::EXPR(Expr::ASSIGNMENT(_, value), _) if matches!(**value, Expr::CONDITIONAL(_, _, _))));
+
+    // a missing `:` is reported at the token found instead
+    let (_, diagnostics) = parse("let a = b ? 1 2;");
+    assert_eq!(diagnostics.len(), 1);
+    let range = &diagnostics[0].range;
+    assert_eq!((range.start.line, range.start.character), (0, 14));
+}
+
+#[test]
+fn test_match_arms_covered_by_an_earlier_arm_are_reported() {
+    use crate::parser::Pattern;
+
+    let (program, diagnostics) = parse("match (x) { 1 => a, \"s\" => { b; }, n => c, _ => d }");
+    match &program[0] {
+        Stmt::MATCH(_, arms) => {
+            assert_eq!(arms.len(), 4);
+            assert!(matches!(arms[0].pattern, Pattern::Literal(_, _)));
+            assert!(matches!(arms[1].body, Stmt::Block(_)));
+            assert!(matches!(&arms[2].pattern, Pattern::Binding(name, _) if name == "n"));
+            assert!(matches!(arms[3].pattern, Pattern::Wildcard(_)));
+        }
+        _ => panic!("expected a match statement"),
+    }
+    // the wildcard after a binding can never match
+    assert_eq!(diagnostics.len(), 1);
+
+    // a repeated literal points back at the arm that already matches it
+    let (_, diagnostics) = parse("match (x) { 1 => a, \"s\" => b, 1 => c, true => d, \"s\" => e }");
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].severity, 2);
+    assert_eq!(diagnostics[0].range.start.character, 30);
+    assert_eq!(diagnostics[0].message, "unreachable match arm, already covered by an earlier arm");
+    assert_eq!(diagnostics[0].related_information[0].location.range.start.character, 12);
+    assert_eq!(diagnostics[1].related_information[0].location.range.start.character, 20);
+
+    // everything after a wildcard or a binding is unreachable, reported once per arm
+    let (_, diagnostics) = parse("match (x) { _ => a, 1 => b, _ => c }\nmatch (x) { n => a, 2 => b }");
+    assert_eq!(diagnostics.len(), 3);
+    assert!(diagnostics.iter().all(|diagnostic| diagnostic.related_information[0].location.range.start.character == 12));
+
+    // different literals, and a catch all at the end, are fine
+    let (_, diagnostics) = parse("match (x) { 1 => a, 2 => b, 1.5 => c, \"1\" => d, false => e, _ => f }");
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+fn test_loader_resolves_imports_and_reports_cycles() {
+    let files: HashMap<PathBuf, &str> = HashMap::from([
+        (PathBuf::from("proj/main.dod"), "import \"lib/a.dod\"; import \"missing.dod\";"),
+        (PathBuf::from("proj/lib/a.dod"), "import \"./b.dod\"; let a = 1;"),
+        (PathBuf::from("proj/lib/b.dod"), "import \"../lib/a.dod\";"),
+    ]);
+    let graph = load_program_with(Path::new("./proj/main.dod"), |path| {
+        files.get(path).map(|source| source.to_string()).ok_or(io::Error::from(io::ErrorKind::NotFound))
+    });
+
+    assert_eq!(graph.order, vec![PathBuf::from("proj/lib/b.dod"), PathBuf::from("proj/lib/a.dod"), PathBuf::from("proj/main.dod")]);
+    assert_eq!(graph.module(Path::new("proj/lib/a.dod")).unwrap().imports, vec![PathBuf::from("proj/lib/b.dod")]);
+
+    let b = graph.module(Path::new("proj/lib/b.dod")).unwrap();
+    assert_eq!(b.import_errors.len(), 1);
+    assert!(matches!(&b.import_errors[0], ImportError::Cycle { cycle, .. } if cycle.len() == 3));
+    assert!(b.import_errors[0].to_string().starts_with("import cycle"));
+
+    let main = graph.module(Path::new("proj/main.dod")).unwrap();
+    assert_eq!(main.import_errors.len(), 1);
+    assert!(matches!(&main.import_errors[0], ImportError::Unresolved { import, .. } if import == "missing.dod"));
+}
+
+#[test]
+fn test_imports_inside_blocks_are_rejected() {
+    use crate::error::ParseError;
+
+    let files: HashMap<PathBuf, &str> = HashMap::from([
+        (PathBuf::from("main.dod"), "import \"a.dod\";\nif (x) {\n    import \"b.dod\";\n}\nfunction f() { { import \"b.dod\"; } }"),
+        (PathBuf::from("a.dod"), "let a = 1;"),
+        (PathBuf::from("b.dod"), "let b = 1;"),
+    ]);
+    let graph = load_program_with(Path::new("main.dod"), |path| {
+        files.get(path).map(|source| source.to_string()).ok_or(io::Error::from(io::ErrorKind::NotFound))
+    });
+
+    // only the top level import is followed, the nested ones are errors instead of being dropped quietly
+    let main = graph.module(Path::new("main.dod")).unwrap();
+    assert_eq!(main.imports, vec![PathBuf::from("a.dod")]);
+    assert!(graph.module(Path::new("b.dod")).is_none());
+    let nested: Vec<(u32, u32)> = main
+        .errors
+        .iter()
+        .map(|error| match error {
+            ParseError::NestedImport { span } => (span.start_line, span.start_column),
+            other => panic!("expected a nested import, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(nested, [(3, 5), (5, 18)]);
+    assert_eq!(main.errors[0].code(), "E0007");
+    assert_eq!(main.errors[0].to_string(), "imports are only allowed at the top level of a file");
+}
+
+#[test]
+fn test_syntax_tree_is_lossless() {
+    let sources = [
+        "let PI = 3.14159265;\n\n// radius in cm\nif (radius > 0) {\n    let area = radius * radius * PI;\n}\n",
+        "let a = b.c(1, 2)[0] * -3 @ ;\n",
+        "let x = (1 + ",
+    ];
+    for source in sources {
+        let tree = crate::syntax::parse(source);
+        assert_eq!(tree.root.text(), source);
+        assert_eq!(tree.root.text_range(), 0..source.len());
+    }
+}
+
+#[test]
+fn test_ast_is_read_off_the_syntax_tree() {
+    use crate::ast::{AstNode, Expr, Program, Stmt};
+    use crate::printer::print_program;
+    use crate::tokenizer::Tokenizer;
+
+    let tree = crate::syntax::parse("if (a > 1) { let b: int = a; }\nlet = 2;");
+    let statements = Program::cast(tree.root.clone()).unwrap().statements();
+    let Stmt::If(stmt) = &statements[0] else { panic!("expected an if statement") };
+    assert!(matches!(stmt.condition(), Some(Expr::Binary(condition)) if condition.syntax().text() == "a > 1"));
+    let body = stmt.body().unwrap().statements();
+    let Stmt::Let(declaration) = &body[0] else { panic!("expected a declaration") };
+    assert_eq!(declaration.name().unwrap().text(), "b");
+    assert_eq!(declaration.annotation().unwrap().name().unwrap().text(), "int");
+    assert!(matches!(declaration.initializer(), Some(Expr::Name(name)) if name.syntax().text() == "a"));
+
+    // the `=` standing in for the name is skipped, it isn't taken for the initializer
+    let Stmt::Let(broken) = &statements[1] else { panic!("expected a declaration") };
+    assert!(broken.name().is_none());
+    assert!(matches!(broken.initializer(), Some(Expr::Literal(literal)) if literal.syntax().text() == "2"));
+
+    let sources = [
+        "import \"lib.dod\";\nstruct P { x: int, y: float }\nfunction f(p: P): int { return p.x * 2; }\nlet q = P { x: 1, y: 2.0 };\nconst n = f(q) > 1 ? a::b : [1, 2][0];\nmatch (n) { 1 => a, \"s\" => { b = !c; }, _ => d }\nwhile (n) { break; continue; }\nx\n",
+        "let x = ;\nif (a > ) {\n    let y = 1;\n",
+        "struct { : int, y }\nfunction (a, : ) { return }\nmatch (x) { => 1, @ => }\nlet p = P { : 1, y };\na.; a:: ; let : = 1;\nimport 5;\n",
+    ];
+    for source in sources {
+        // the parser emits the same arena directly as the one built from the program read off the tree
+        let tree = crate::syntax::parse(source);
+        let (ast, errors) = crate::arena::Ast::parse(&Tokenizer::tokenize(source));
+        assert_eq!(ast, crate::arena::Ast::from_program(&tree.program));
+        assert_eq!(errors, tree.errors);
+
+        // printing the program read off the tree and parsing that again gives the same program
+        let printed = print_program(&tree.program);
+        assert_eq!(print_program(&crate::syntax::parse(&printed).program), printed);
+    }
+}
+
+#[test]
+fn test_parser_recovers_from_incomplete_code() {
+    use crate::parser::{Expr, Stmt};
+
+    let tree = crate::syntax::parse("let x = ;\nif (a > ) {\n    let y = 1;\n");
+    assert_eq!(tree.program.len(), 2);
+    assert!(matches!(&tree.program[0], Stmt::VariableDeclaration(declaration)
+        if declaration.name == "x" && declaration.initializer == Some(Expr::Error)));
+    match &tree.program[1] {
+        Stmt::IFSTATEMENT(Expr::BINARYEXPR(_, right, _, _), body) => {
+            assert!(matches!(**right, Expr::Error));
+            assert!(matches!(&**body, Stmt::Block(statements) if statements.len() == 1));
+        }
+        _ => panic!("expected an if statement"),
+    }
+    assert_eq!(tree.errors.len(), 3);
+
+    // a statement that can't be parsed is skipped, braces included, and parsing goes on after it
+    let tree = crate::syntax::parse("let a = 1; for (x) { a; } let b = a;");
+    assert_eq!(tree.program.len(), 2);
+    assert!(matches!(&tree.program[1], Stmt::VariableDeclaration(declaration) if declaration.name == "b"));
+}
+
+#[test]
+fn test_incremental_reparse_matches_full_parse() {
+    use std::rc::Rc;
+    use crate::incremental::Document;
+    use crate::syntax::GreenElement;
+    use crate::types::{Position, Range};
+
+    let source = "let PI = 3.14;\nlet r = 5;\nif (r > 0) {\n    let area = r * r * PI;\n}\nlet s = [1, 2][0];\n";
+    let edits = [
+        // (line, column) to (line, column), replacement
+        ((1, 8), (1, 9), "50"),
+        ((3, 21), (3, 25), " + "),
+        ((2, 10), (2, 12), ""),
+        ((2, 10), (2, 10), "{\n"),
+        ((0, 0), (0, 0), "\"open"),
+        ((0, 0), (0, 5), ""),
+        ((5, 0), (5, 0), "match (r) { 1 => a, _ => b }\n"),
+    ];
+
+    let mut document = Document::new(source);
+    let mut text = source.to_string();
+    for ((start_line, start_column), (end_line, end_column), replacement) in edits {
+        let range = Range {
+            start: Position { line: start_line, character: start_column },
+            end: Position { line: end_line, character: end_column },
+        };
+        let offset = |line: u32, column: u32| text.split_inclusive('\n').take(line as usize).map(|l| l.len()).sum::<usize>() + column as usize;
+        let (start, end) = (offset(start_line, start_column), offset(end_line, end_column));
+        text.replace_range(start..end, replacement);
+        document.edit(range, replacement);
+
+        let full = Document::new(&text);
+        assert_eq!(document.source(), text);
+        assert_eq!(document.tree().root.green(), full.tree().root.green());
+        assert_eq!(document.tree().program, full.tree().program);
+        assert_eq!(document.tree().errors, full.tree().errors);
+    }
+
+    // statements away from an edit keep their nodes
+    let mut document = Document::new(source);
+    let before: Vec<GreenElement> = document.tree().root.green().children.clone();
+    document.edit(Range { start: Position { line: 3, character: 15 }, end: Position { line: 3, character: 16 } }, "radius");
+    let after = &document.tree().root.green().children;
+    let same = |a: &GreenElement, b: &GreenElement| match (a, b) {
+        (GreenElement::Node(a), GreenElement::Node(b)) => Rc::ptr_eq(a, b),
+        _ => false,
+    };
+    // children are the four statements with whitespace in between, the edit is inside the `if`
+    assert!(same(&before[0], &after[0]));
+    assert!(same(&before[2], &after[2]));
+    assert!(!same(&before[4], &after[4]));
+    assert!(same(&before[6], &after[6]));
+}
+
+#[test]
+fn test_arena_of_an_edited_document_matches_a_fresh_parse() {
+    use crate::arena::Ast;
+    use crate::incremental::Document;
+    use crate::tokenizer::Tokenizer;
+    use crate::types::{Position, Range};
+
+    // the language server builds the arena off the incrementally maintained program
+    let mut document = Document::new("let a = 1;\nlet b = a + 2;\nif (b > 2) { a = b; }\n");
+    let edits = [((1, 8), (1, 9), "c"), ((0, 0), (0, 0), "let c = 2.5;\n"), ((3, 11), (3, 21), "{ b = c * a; }")];
+    for ((start_line, start_column), (end_line, end_column), replacement) in edits {
+        let range = Range {
+            start: Position { line: start_line, character: start_column },
+            end: Position { line: end_line, character: end_column },
+        };
+        document.edit(range, replacement);
+        let (fresh, errors) = Ast::parse(&Tokenizer::tokenize(document.source()));
+        assert_eq!(Ast::from_program(&document.tree().program), fresh);
+        assert_eq!(document.tree().errors, errors);
+    }
+}
+
+#[test]
+fn test_visitors_reach_every_expression() {
+    use crate::parser::{Expr, Stmt};
+    use crate::visit::{walk_expr, walk_expr_mut, Visitor, VisitorMut};
+
+    struct Names(Vec<String>);
+    impl Visitor for Names {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let Expr::IDENTIFIER(name, _) = expr {
+                self.0.push(name.to_string());
+            }
+            walk_expr(self, expr);
+        }
+    }
+
+    struct Rename;
+    impl VisitorMut for Rename {
+        fn visit_expr_mut(&mut self, expr: &mut Expr) {
+            if let Expr::IDENTIFIER(name, _) = expr {
+                name.insert(0, '_');
+            }
+            walk_expr_mut(self, expr);
+        }
+    }
+
+    let mut tree = crate::syntax::parse("let a = b[c] + f(d).e;\nif (g) { h = i ? j : P { x: k }; }\nmatch (l) { 1 => m, _ => { [n]; } }\n");
+    let mut names = Names(Vec::new());
+    names.visit_program(&tree.program);
+    assert_eq!(names.0, ["b", "c", "f", "d", "g", "h", "i", "j", "k", "l", "m", "n"]);
+
+    Rename.visit_program_mut(&mut tree.program);
+    match &tree.program[1] {
+        Stmt::IFSTATEMENT(Expr::IDENTIFIER(name, _), _) => assert_eq!(name, "_g"),
+        _ => panic!("expected an if statement"),
+    }
+    let mut names = Names(Vec::new());
+    names.visit_program(&tree.program);
+    assert!(names.0.iter().all(|name| name.starts_with('_')));
+}
+
+#[test]
+fn test_tokens_and_ast_round_trip_through_json() {
+    use crate::parser::Stmt;
+    use crate::tokenizer::{Token, Tokenizer};
+
+    let source = "let a = (1 + 2.5) * b[0];\nstruct P { x: int }\nmatch (\"s\") { \"s\" => P { x: 1 }, _ => !c.d(e) }\nimport \"lib.dod\";\n";
+    let tokens = Tokenizer::tokenize(source);
+    let json = serde_json::to_string(&tokens).unwrap();
+    assert_eq!(serde_json::from_str::<Vec<Token>>(&json).unwrap(), tokens);
+
+    let tree = crate::syntax::parse(source);
+    let json = serde_json::to_string(&tree.program).unwrap();
+    assert_eq!(serde_json::from_str::<Vec<Stmt>>(&json).unwrap(), tree.program);
+
+    // the representation other tools rely on
+    let tree = crate::syntax::parse("x + 1;");
+    assert_eq!(
+        serde_json::to_string(&tree.program).unwrap(),
+        r#"[{"EXPR":[{"BINARYEXPR":[{"IDENTIFIER":["x",{"start_line":1,"start_column":1,"end_line":1,"end_column":1}]},{"INTEGERLITERAL":[1,{"start_line":1,"start_column":5,"end_line":1,"end_column":5}]},"ADD",{"start_line":1,"start_column":3,"end_line":1,"end_column":3}]},{"start_line":1,"start_column":1,"end_line":1,"end_column":5}]}]"#
+    );
+}
+
+#[test]
+fn test_initialization_options_keep_their_client_names() {
+    use crate::types::initialization::InitializeRequest;
+
+    let request: InitializeRequest = serde_json::from_str(r#"{"params":{"initializationOptions":{"warnShadowing":true}}}"#).unwrap();
+    assert!(request.params.initializationOptions.unwrap().warn_shadowing);
+    let request: InitializeRequest = serde_json::from_str(r#"{"params":{"initializationOptions":{}}}"#).unwrap();
+    assert!(!request.params.initializationOptions.unwrap().warn_shadowing);
+}
+
+#[test]
+fn test_printer_uses_minimal_parentheses() {
+    use crate::parser::{Expr, Stmt};
+    use crate::printer::print_program;
+    use crate::tokenizer::Span;
+    use crate::visit::{walk_expr_mut, VisitorMut};
+
+    // positions change with the layout and only the parentheses the precedence
+    // needs are printed, everything else has to survive printing
+    struct Unplaced;
+    impl VisitorMut for Unplaced {
+        fn visit_expr_mut(&mut self, expr: &mut Expr) {
+            while let Expr::PARENTHESIZED(inner) = expr {
+                *expr = std::mem::replace(inner.as_mut(), Expr::Error);
+            }
+            walk_expr_mut(self, expr);
+        }
+
+        fn visit_span_mut(&mut self, span: &mut Span) {
+            *span = Span { start_line: 0, start_column: 0, end_line: 0, end_column: 0 };
+        }
+    }
+    let unplaced = |mut program: Vec<Stmt>| {
+        Unplaced.visit_program_mut(&mut program);
+        program
+    };
+
+    let cases = [
+        ("let a = (1 + 2) * 3;", "let a = (1 + 2) * 3;\n"),
+        ("let a:float=1;", "let a: float = 1;\n"),
+        ("const PI=3.14;", "const PI = 3.14;\n"),
+        ("while(x){break;continue;}return x+1;", "while (x) {\n    break;\n    continue;\n}\n\nreturn x + 1;\n"),
+        ("let a = 1 + (2 * 3);", "let a = 1 + 2 * 3;\n"),
+        ("a - (b - c); (a - b) - c;", "a - (b - c);\na - b - c;\n"),
+        ("x = (y = z); (a ? b : c) ? d : e; a ? b : (c ? d : e);", "x = y = z;\n(a ? b : c) ? d : e;\na ? b : c ? d : e;\n"),
+        ("!(a.b)[0]; let p = P { x: (1), y: [(2 + 3) * 4] };", "!a.b[0];\nlet p = P { x: 1, y: [(2 + 3) * 4] };\n"),
+        (
+            "let r = 5;\nif (r > 0) { let area = r * r; match (area) { 1 => a, _ => { b; } } }",
+            "let r = 5;\n\nif (r > 0) {\n    let area = r * r;\n\n    match (area) {\n        1 => a,\n        _ => {\n            b;\n        }\n    }\n}\n",
+        ),
+    ];
+    for (source, expected) in cases {
+        let program = crate::syntax::parse(source).program;
+        let printed = print_program(&program);
+        assert_eq!(printed, expected);
+        // the output parses back into the same program
+        assert_eq!(unplaced(crate::syntax::parse(&printed).program), unplaced(program));
+    }
+}
+
+#[test]
+fn test_arena_ast_mirrors_boxed_ast() {
+    use crate::arena::{self, Ast, ExprId, ExprNode, StmtNode};
+
+    struct Names<'a>(&'a Ast, Vec<String>);
+
+    impl<'a> arena::Visitor<'a> for Names<'a> {
+        fn ast(&self) -> &'a Ast {
+            self.0
+        }
+
+        fn visit_expr(&mut self, expr: ExprId) {
+            if let ExprNode::IDENTIFIER(name, _) = self.0.expr(expr) {
+                self.1.push(self.0.name(*name).to_string());
+            }
+            arena::walk_expr(self, expr);
+        }
+    }
+
+    let source = "let a = b + c * d;\nif (a) { f(a, [b, c]); }\nmatch (x) { 1 => y, n => { z = n; } }";
+    let program = crate::syntax::parse(source).program;
+    let ast = Ast::from_program(&program);
+
+    let mut names = Names(&ast, Vec::new());
+    arena::Visitor::visit_program(&mut names, ast.program());
+    assert_eq!(names.1, ["b", "c", "d", "a", "f", "a", "b", "c", "x", "y", "z", "n"]);
+
+    // names are interned once, lists of children keep their order
+    assert!(ast.symbol("a").is_some());
+    assert_eq!(ast.symbol("missing"), None);
+    match *ast.stmt(ast.program()[1]) {
+        StmtNode::IFSTATEMENT(_, body) => match *ast.stmt(body) {
+            StmtNode::Block(statements) => assert_eq!(ast.stmt_list(statements).len(), 1),
+            ref other => panic!("expected a block, got {:?}", other),
+        },
+        ref other => panic!("expected an if statement, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_errors_carry_codes_and_expected_tokens() {
+    use crate::error::{Expected, ParseError, Severity};
+    use crate::tokenizer::TokenKind;
+    use crate::types::diagnostics::Diagnostic;
+
+    let tree = crate::syntax::parse("let 5 = 1;\nf(a b);\nx = [1, 2;\nmatch (x) { 1 => a, 1 => b }\nfor");
+    let codes: Vec<&str> = tree.errors.iter().map(|error| error.code()).collect();
+    assert_eq!(codes, ["E0001", "E0001", "E0003", "W0001", "E0004"]);
+
+    match &tree.errors[0] {
+        ParseError::UnexpectedToken { found: TokenKind::INT(Some(5)), expected, context, span } => {
+            assert_eq!(expected, &[Expected::Identifier]);
+            assert_eq!(*context, "variable declaration");
+            assert_eq!((span.start_line, span.start_column), (1, 5));
+        }
+        other => panic!("expected an unexpected token error, got {:?}", other),
+    }
+    // a missing comma could also have been the end of the list
+    assert_eq!(tree.errors[1].expected(), &[Expected::Token(TokenKind::COMMA), Expected::Token(TokenKind::RPAREN)]);
+    assert_eq!(tree.errors[1].to_string(), "invalid syntax for list, expected ',' or ')' found identifier 'b'");
+    assert!(matches!(&tree.errors[2], ParseError::UnclosedDelimiter { delimiter: TokenKind::RSQUARE, found: TokenKind::SEMICOLON, .. }));
+    assert_eq!(tree.errors[3].severity(), Severity::Warning);
+
+    let tree = crate::syntax::parse("let x = (1 + ");
+    assert!(matches!(&tree.errors[0], ParseError::UnexpectedEof { expected, .. } if expected == &[Expected::Expression]));
+
+    // the editor gets the same text along with the code
+    let diagnostic = Diagnostic::from(&tree.errors[0]);
+    assert_eq!(diagnostic.message, tree.errors[0].to_string());
+    assert_eq!(diagnostic.code.as_deref(), Some("E0002"));
+    assert_eq!(diagnostic.severity, 1);
+}
@@ -6,27 +6,76 @@ use std::{
     iter::Peekable, str::Bytes,
 };
 
-#[derive(Debug)]
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Position, Range};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum TokenKind {
     SEMICOLON, COLON, DCOLON, LPAREN, RPAREN, LCURLY, RCURLY, LSQUARE, RSQUARE, COMMA, DOT,
 
     ADD, SUB, MUL, DIV, MOD,
 
-    INT(i32), FLOAT(f32), STRING(String), IDENTIFIER(String), KEYWORD(String),
+    // `None` for a literal too large for an int, the parser reports it
+    INT(Option<i32>), FLOAT(f32), STRING(String), IDENTIFIER(String), KEYWORD(String),
 
     BANG,
+    QUESTION,
     ASSIGNE,
+    FATARROW,
 
     EQ, GT, LT, GTEQ, LTEQ, NEQ,
 
     EOF,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Token {
     pub kind: TokenKind,
     pub line: u32,
-    pub column: u32
+    pub column: u32,
+    pub start_line: u32,
+    pub start_column: u32
+}
+
+// a region of source code, lines and columns are 1 based like the ones on
+// `Token` and both ends are inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Span {
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32
+}
+
+impl Span {
+    // the smallest span covering both `self` and `other`, `other` is expected to come after `self`
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            start_line: self.start_line,
+            start_column: self.start_column,
+            end_line: other.end_line,
+            end_column: other.end_column
+        }
+    }
+
+    pub fn range(&self) -> Range {
+        Range {
+            start: Position { line: self.start_line.saturating_sub(1), character: self.start_column.saturating_sub(1) },
+            end: Position { line: self.end_line.saturating_sub(1), character: self.end_column }
+        }
+    }
+}
+
+impl Token {
+    pub fn span(&self) -> Span {
+        Span {
+            start_line: self.start_line,
+            start_column: self.start_column,
+            end_line: self.line,
+            end_column: self.column
+        }
+    }
 }
 
 
@@ -55,7 +104,9 @@ impl Clone for TokenKind {
             TokenKind::IDENTIFIER(val) => TokenKind::IDENTIFIER(val.clone()),
             TokenKind::KEYWORD(val) => TokenKind::KEYWORD(val.clone()),
             TokenKind::BANG => TokenKind::BANG,
+            TokenKind::QUESTION => TokenKind::QUESTION,
             TokenKind::ASSIGNE => TokenKind::ASSIGNE,
+            TokenKind::FATARROW => TokenKind::FATARROW,
             TokenKind::EQ => TokenKind::EQ,
             TokenKind::GT => TokenKind::GT,
             TokenKind::LT => TokenKind::LT,
@@ -72,7 +123,9 @@ impl Clone for Token {
         Token {
             kind: self.kind.clone(),
             line: self.line.clone(),
-            column: self.column.clone()
+            column: self.column.clone(),
+            start_line: self.start_line,
+            start_column: self.start_column
         }
     }
 }
@@ -81,7 +134,9 @@ pub struct Tokenizer<'a> {
     iterator: Peekable<Bytes<'a>>,
     current_token: Option<Token>,
     current_line: u32,
-    current_col: u32
+    current_col: u32,
+    token_line: u32,
+    token_col: u32
 }
 
 impl<'a> Tokenizer<'a> {
@@ -105,8 +160,13 @@ impl<'a> Tokenizer<'a> {
         let mut tokenizer = Tokenizer {
             iterator: codeText,
             current_token: None,
-            current_col: 1,
-            current_line: 1
+            // the column of the last consumed byte. `next_byte` counts before
+            // returning and a newline resets to 0, so starting at 0 puts the first
+            // byte of every line, the first one included, at column 1
+            current_col: 0,
+            current_line: 1,
+            token_line: 1,
+            token_col: 0
         };
 
         tokenizer.parse_token();
@@ -125,7 +185,7 @@ impl<'a> Tokenizer<'a> {
         // define a hashset of keywords
         let keywords: HashSet<&str> = HashSet::from([
             "if", "else", "while", "for", "return", "break", "continue", "true", "false", "null",
-            "int", "float", "string", "bool", "void", "let", "const", "function",
+            "int", "float", "string", "bool", "void", "let", "const", "function", "struct", "match", "import",
         ]);
 
         loop {
@@ -145,17 +205,9 @@ impl<'a> Tokenizer<'a> {
         }
 
         if keywords.contains(word.as_str()) {
-            return Token {
-                kind: TokenKind::KEYWORD(word),
-                line: self.current_line,
-                column: self.current_col
-            };
+            return self.make_token(TokenKind::KEYWORD(word));
         } else {
-            return Token {
-                kind: TokenKind::IDENTIFIER(word),
-                line: self.current_line,
-                column: self.current_col
-            };
+            return self.make_token(TokenKind::IDENTIFIER(word));
         }
     }
 
@@ -228,6 +280,17 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    // builds a token that started where the current one was first seen and ends at the last consumed byte
+    fn make_token(&self, kind: TokenKind) -> Token {
+        Token {
+            kind,
+            line: self.current_line,
+            column: self.current_col,
+            start_line: self.token_line,
+            start_column: self.token_col
+        }
+    }
+
     pub fn peek_token(&self) -> Option<Token> {
         return self.current_token.clone();
     }
@@ -248,12 +311,18 @@ impl<'a> Tokenizer<'a> {
 
 
         let byte = self.next_byte();
+        self.token_line = self.current_line;
+        self.token_col = self.current_col;
 
         if let None = byte {
+            // the end of the file is the column after the last byte, or the first one of an empty last line
+            self.token_col = self.current_col + 1;
             self.current_token = Some(Token {
                 kind: TokenKind::EOF,
                 line: self.current_line,
-                column: self.current_col
+                column: self.token_col,
+                start_line: self.token_line,
+                start_column: self.token_col
             });
             return;
         }
@@ -261,186 +330,106 @@ impl<'a> Tokenizer<'a> {
         let byte = byte.unwrap();
 
         if byte == b';' {
-            self.current_token = Some(Token {
-                kind: TokenKind::SEMICOLON,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::SEMICOLON));
         } else if byte == b':' {
             match self.iterator.peek() {
                 Some(b':') => {
-                    self.current_token = Some(Token {
-                        kind: TokenKind::DCOLON,
-                        line: self.current_line,
-                        column: self.current_col
-                    });
                     self.next_byte();
+                    self.current_token = Some(self.make_token(TokenKind::DCOLON));
                 }
                 _ => {
-                    self.current_token = Some(Token {
-                        kind: TokenKind::COLON,
-                        line: self.current_line,
-                        column: self.current_col
-                    });
+                    self.current_token = Some(self.make_token(TokenKind::COLON));
                 }
             }
         } else if byte == b',' {
-            self.current_token = Some(Token {
-                kind: TokenKind::COMMA,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::COMMA));
         } else if byte == b'(' {
-            self.current_token = Some(Token {
-                kind: TokenKind::LPAREN,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::LPAREN));
         } else if byte == b')' {
-            self.current_token = Some(Token {
-                kind: TokenKind::RPAREN,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::RPAREN));
         } else if byte == b'{' {
-            self.current_token = Some(Token {
-                kind: TokenKind::LCURLY,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::LCURLY));
         } else if byte == b'}' {
-            self.current_token = Some(Token {
-                kind: TokenKind::RCURLY,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::RCURLY));
         } else if byte == b'[' {
-            self.current_token = Some(Token {
-                kind: TokenKind::LSQUARE,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::LSQUARE));
         } else if byte == b']' {
-            self.current_token = Some(Token {
-                kind: TokenKind::RSQUARE,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::RSQUARE));
         } else if byte == b'.' {
-            self.current_token = Some(Token {
-                kind: TokenKind::DOT,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::DOT));
         } else if byte == b'!' {
             match self.iterator.peek() {
                 Some(b'=') => {
-                    self.current_token = Some(Token {
-                        kind: TokenKind::NEQ,
-                        line: self.current_line,
-                        column: self.current_col
-                    });
                     self.next_byte();
+                    self.current_token = Some(self.make_token(TokenKind::NEQ));
                 }
                 _ => {
-                    self.current_token = Some(Token {
-                        kind: TokenKind::BANG,
-                        line: self.current_line,
-                        column: self.current_col
-                    })
+                    self.current_token = Some(self.make_token(TokenKind::BANG))
                 }
             }
+        } else if byte == b'?' {
+            self.current_token = Some(self.make_token(TokenKind::QUESTION));
         } else if byte == b'=' {
             match self.iterator.peek() {
                 Some(b'=') => {
-                    self.current_token = Some(Token {
-                        kind: TokenKind::EQ,
-                        line: self.current_line,
-                        column: self.current_col
-                    });
                     self.next_byte();
+                    self.current_token = Some(self.make_token(TokenKind::EQ));
+                }
+                Some(b'>') => {
+                    self.next_byte();
+                    self.current_token = Some(self.make_token(TokenKind::FATARROW));
                 }
                 _ => {
-                    self.current_token = Some(Token {
-                        kind: TokenKind::ASSIGNE,
-                        line: self.current_line,
-                        column: self.current_col
-                    })
+                    self.current_token = Some(self.make_token(TokenKind::ASSIGNE))
                 }
             }
         } else if byte == b'<' {
             match self.iterator.peek() {
                 Some(b'=') => {
-                    self.current_token = Some(Token {
-                        kind: TokenKind::LTEQ,
-                        line: self.current_line,
-                        column: self.current_col
-                    });
                     self.next_byte();
+                    self.current_token = Some(self.make_token(TokenKind::LTEQ));
                 }
                 _ => {
-                    self.current_token = Some(Token {
-                        kind: TokenKind::LT,
-                        line: self.current_line,
-                        column: self.current_col
-                    })
+                    self.current_token = Some(self.make_token(TokenKind::LT))
                 }
             }
         } else if byte == b'>' {
             match self.iterator.peek() {
                 Some(b'=') => {
-                    self.current_token = Some(Token {
-                        kind: TokenKind::GTEQ,
-                        line: self.current_line,
-                        column: self.current_col
-                    });
                     self.next_byte();
+                    self.current_token = Some(self.make_token(TokenKind::GTEQ));
                 }
                 _ => {
-                    self.current_token = Some(Token {
-                        kind: TokenKind::GT,
-                        line: self.current_line,
-                        column: self.current_col
-                    })
+                    self.current_token = Some(self.make_token(TokenKind::GT))
                 }
             }
         } else if byte == b'"' {
             let string = self.get_string();
-            self.current_token = Some(Token {
-                kind: TokenKind::STRING(string),
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::STRING(string)));
         } else if byte == b'+' {
-            self.current_token = Some(Token {
-                kind: TokenKind::ADD,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::ADD));
         } else if byte == b'-' {
-            self.current_token = Some(Token {
-                kind: TokenKind::SUB,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::SUB));
         } else if byte == b'*' {
-            self.current_token = Some(Token {
-                kind: TokenKind::MUL,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::MUL));
         } else if byte == b'/' {
-            self.current_token = Some(Token {
-                kind: TokenKind::DIV,
-                line: self.current_line,
-                column: self.current_col
-            });
+            match self.iterator.peek() {
+                Some(b'/') => {
+                    // line comment, skipped up to the end of the line
+                    while let Some(byte) = self.iterator.peek() {
+                        if *byte == b'\n' {
+                            break;
+                        }
+                        self.next_byte();
+                    }
+                    self.parse_token();
+                }
+                _ => {
+                    self.current_token = Some(self.make_token(TokenKind::DIV));
+                }
+            }
         } else if byte == b'%' {
-            self.current_token = Some(Token {
-                kind: TokenKind::MOD,
-                line: self.current_line,
-                column: self.current_col
-            });
+            self.current_token = Some(self.make_token(TokenKind::MOD));
         } else if byte == b' ' || byte == b'\t' || byte == b'\r' || byte == b'\n' {
             self.parse_token();
         } else if char::from(byte).is_numeric() {
@@ -451,26 +440,17 @@ impl<'a> Tokenizer<'a> {
                     Err(_) => 0.0,
                 };
 
-                self.current_token = Some(Token {
-                    kind: TokenKind::FLOAT(val),
-                    line: self.current_line,
-                    column: self.current_col
-                });
+                self.current_token = Some(self.make_token(TokenKind::FLOAT(val)));
             } else {
-                let val: i32 = match value.parse() {
-                    Ok(v) => v,
-                    Err(_) => 0,
-                };
-
-                self.current_token = Some(Token {
-                    kind: TokenKind::INT(val),
-                    line: self.current_line,
-                    column: self.current_col
-                });
+                // only digits were read, so the literal can only fail to parse by being too large
+                self.current_token = Some(self.make_token(TokenKind::INT(value.parse().ok())));
             }
         } else if byte == b'_' || char::from(byte).is_alphabetic() {
             let token = self.get_word(byte);
             self.current_token = Some(token);
+        } else {
+            // unknown bytes are skipped, the syntax tree keeps them as error tokens
+            self.parse_token();
         }
     }
 
@@ -0,0 +1,615 @@
+// static type checking.
+//
+// `check_types` runs after `resolver::resolve` and gives every variable the type
+// of its annotation, or of its initializer when there is none. `int` is promoted
+// to `float` wherever the two meet, comparisons are `bool` and conditions have
+// to be `bool`. a call has to pass as many arguments as the function has
+// parameters, each of the parameter's type, and a `return` has to give a value
+// of the function's return type. an expression whose type can't be worked out,
+// because it has a syntax error or names something undefined, is `Unknown`,
+// which is accepted everywhere so a single mistake is only reported once.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::error::SemanticError;
+use crate::parser::{covering, Expr, FieldDeclaration, FunctionDeclaration, Pattern, Stmt, TypeAnnotation, VariableDeclaration};
+use crate::printer::print_expr;
+use crate::resolver::{DeclarationId, Resolution};
+use crate::tokenizer::{Span, TokenKind};
+use crate::visit::{self, Visitor};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Type {
+    Int,
+    Float,
+    String,
+    Bool,
+    Void,
+    Struct(String),
+    Array(Box<Type>),
+    // the parameter types and the return type
+    Function(Vec<Type>, Box<Type>),
+    Unknown,
+}
+
+impl Type {
+    fn is_numeric(&self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+
+    fn is_known(&self) -> bool {
+        !matches!(self, Type::Unknown)
+    }
+
+    // whether a value of type `found` can be stored where `self` is expected
+    pub fn accepts(&self, found: &Type) -> bool {
+        match (self, found) {
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            (Type::Float, Type::Int) => true,
+            (Type::Array(expected), Type::Array(found)) => expected.accepts(found),
+            (expected, found) => expected == found,
+        }
+    }
+
+    // the type both sides of `==` or a conditional end up as, `None` if they don't meet
+    fn common(&self, other: &Type) -> Option<Type> {
+        match (self, other) {
+            (Type::Unknown, _) | (_, Type::Unknown) => Some(Type::Unknown),
+            (Type::Int, Type::Float) | (Type::Float, Type::Int) => Some(Type::Float),
+            (Type::Array(left), Type::Array(right)) => left.common(right).map(|element| Type::Array(Box::new(element))),
+            (left, right) if left == right => Some(left.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Void => write!(f, "void"),
+            Type::Struct(name) => write!(f, "{}", name),
+            Type::Array(element) => write!(f, "[{}]", element),
+            Type::Function(parameters, returns) => {
+                let parameters: Vec<String> = parameters.iter().map(|parameter| parameter.to_string()).collect();
+                write!(f, "function({}): {}", parameters.join(", "), returns)
+            }
+            Type::Unknown => write!(f, "{{unknown}}"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Typing {
+    // the type of every variable, match binding, function and parameter
+    pub variables: HashMap<DeclarationId, Type>,
+    pub errors: Vec<SemanticError>,
+}
+
+impl Typing {
+    pub fn variable(&self, id: DeclarationId) -> &Type {
+        self.variables.get(&id).unwrap_or(&Type::Unknown)
+    }
+}
+
+pub fn check_types(program: &[Stmt], resolution: &Resolution) -> Typing {
+    let mut structs = Structs(HashMap::new());
+    structs.visit_program(program);
+
+    let mut checker = Checker {
+        resolution,
+        declarations: resolution
+            .declarations
+            .iter()
+            .enumerate()
+            .map(|(index, declaration)| (declaration.span, DeclarationId(index)))
+            .collect(),
+        structs: structs.0,
+        signatures: HashMap::new(),
+        returns: Vec::new(),
+        typing: Typing::default(),
+    };
+    checker.visit_program(program);
+    checker.typing
+}
+
+// the fields of every struct declared anywhere in the program, by struct name
+struct Structs(HashMap<String, Vec<(String, String)>>);
+
+impl Visitor for Structs {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::StructDeclaration(name, fields) = stmt {
+            let fields = fields.iter().map(|field| (field.name.to_string(), field.type_name.to_string())).collect();
+            self.0.insert(name.to_string(), fields);
+        }
+        visit::walk_stmt(self, stmt);
+    }
+}
+
+struct Checker<'a> {
+    resolution: &'a Resolution,
+    declarations: HashMap<Span, DeclarationId>,
+    structs: HashMap<String, Vec<(String, String)>>,
+    // the signature of every function, for pointing at it from a call
+    signatures: HashMap<DeclarationId, Span>,
+    // the return type of every function around the current statement
+    returns: Vec<Type>,
+    typing: Typing,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, error: SemanticError) {
+        self.typing.errors.push(error);
+    }
+
+    // an error about `expr`, dropped when the expression has no position to report
+    fn error_at(&mut self, expr: &Expr, error: impl FnOnce(Span) -> SemanticError) {
+        if let Some(span) = expr.span() {
+            self.error(error(span));
+        }
+    }
+
+    // the type a type name written in the source stands for, `None` if nothing has that name
+    fn named(&self, name: &str) -> Option<Type> {
+        let ty = match name {
+            "int" => Type::Int,
+            "float" => Type::Float,
+            "string" => Type::String,
+            "bool" => Type::Bool,
+            "void" => Type::Void,
+            // the parser already reported a missing type
+            "" => Type::Unknown,
+            name if self.structs.contains_key(name) => Type::Struct(name.to_string()),
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    fn field(&self, ty: &Type, field: &str) -> Option<Type> {
+        let Type::Struct(name) = ty else { return None };
+        let (_, type_name) = self.structs.get(name)?.iter().find(|(name, _)| name == field)?;
+        Some(self.named(type_name).unwrap_or(Type::Unknown))
+    }
+
+    fn declare(&mut self, span: Span, ty: Type) {
+        if let Some(id) = self.declarations.get(&span) {
+            self.typing.variables.insert(*id, ty);
+        }
+    }
+
+    // `expr` has to be usable where `expected` is
+    fn expect(&mut self, expected: Type, expr: &Expr) {
+        let found = self.infer(expr);
+        if !expected.accepts(&found) {
+            self.error_at(expr, |span| SemanticError::TypeMismatch { expected, found, span });
+        }
+    }
+
+    // the type an annotation names, reporting a name that isn't a type
+    fn annotated(&mut self, annotation: &TypeAnnotation) -> Type {
+        match self.named(&annotation.name) {
+            Some(ty) => ty,
+            None => {
+                self.error(SemanticError::UnknownType { name: annotation.name.to_string(), span: annotation.span });
+                Type::Unknown
+            }
+        }
+    }
+
+    fn variable_declaration(&mut self, declaration: &VariableDeclaration) {
+        let found = match &declaration.initializer {
+            Some(initializer) => self.infer(initializer),
+            None => Type::Unknown,
+        };
+        let ty = match &declaration.annotation {
+            None => found,
+            Some(annotation) => {
+                let annotated = self.annotated(annotation);
+                if !annotated.accepts(&found) {
+                    let span = declaration.initializer.as_ref().and_then(Expr::span).unwrap_or(declaration.span);
+                    self.error(SemanticError::AnnotationMismatch {
+                        name: declaration.name.to_string(),
+                        annotation: annotated.clone(),
+                        found,
+                        span,
+                    });
+                }
+                annotated
+            }
+        };
+        self.declare(declaration.span, ty);
+    }
+
+    // gives the functions among `statements` their types before any of the
+    // statements is checked, calls can come before the function they call
+    fn hoist(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            if let Stmt::FunctionDeclaration(function) = stmt {
+                self.signature(function);
+            }
+        }
+    }
+
+    fn signature(&mut self, function: &FunctionDeclaration) {
+        let mut parameters = Vec::new();
+        for parameter in &function.parameters {
+            let ty = self.annotated(&parameter.annotation);
+            self.declare(parameter.span, ty.clone());
+            parameters.push(ty);
+        }
+        let returns = match &function.return_type {
+            Some(return_type) => self.annotated(return_type),
+            None => Type::Void,
+        };
+        self.declare(function.span, Type::Function(parameters, Box::new(returns.clone())));
+        if let Some(id) = self.declarations.get(&function.span) {
+            self.signatures.insert(*id, function.signature);
+        }
+    }
+
+    // the signature is known from `hoist`, only the body is left
+    fn function_declaration(&mut self, function: &FunctionDeclaration) {
+        let returns = match self.declarations.get(&function.span).and_then(|id| self.typing.variables.get(id)) {
+            Some(Type::Function(_, returns)) => returns.as_ref().clone(),
+            _ => Type::Unknown,
+        };
+        self.returns.push(returns);
+        self.visit_stmt(&function.body);
+        self.returns.pop();
+    }
+
+    fn call(&mut self, expr: &Expr, callee: &Expr, arguments: &[Expr]) -> Type {
+        let (parameters, returns) = match self.infer(callee) {
+            Type::Function(parameters, returns) => (parameters, *returns),
+            found => {
+                if found.is_known() {
+                    self.error_at(callee, |span| SemanticError::NotCallable { found, span });
+                }
+                for argument in arguments {
+                    self.infer(argument);
+                }
+                return Type::Unknown;
+            }
+        };
+
+        let function = print_expr(callee);
+        let signature = match callee {
+            Expr::IDENTIFIER(_, span) => {
+                self.resolution.references.get(span).and_then(|id| self.signatures.get(id)).copied()
+            }
+            _ => None,
+        };
+        for (index, argument) in arguments.iter().enumerate() {
+            let found = self.infer(argument);
+            let Some(expected) = parameters.get(index) else { continue };
+            if !expected.accepts(&found) {
+                self.error_at(argument, |span| SemanticError::ArgumentMismatch {
+                    function: function.clone(),
+                    expected: expected.clone(),
+                    found,
+                    span,
+                    signature,
+                });
+            }
+        }
+
+        if arguments.len() != parameters.len() {
+            let extra = arguments.get(parameters.len()..).unwrap_or_default();
+            let span = match extra {
+                [] => expr.span(),
+                [first, ..] => covering(first.span(), extra.last().and_then(Expr::span)),
+            };
+            if let Some(span) = span.or(callee.span()) {
+                self.error(SemanticError::ArgumentCount {
+                    function,
+                    expected: parameters.len(),
+                    found: arguments.len(),
+                    span,
+                    signature,
+                });
+            }
+        }
+        returns
+    }
+
+    fn infer(&mut self, expr: &Expr) -> Type {
+        match expr {
+            Expr::INTEGERLITERAL(_, _) => Type::Int,
+            Expr::FLOATLITERAL(_, _) => Type::Float,
+            Expr::STRINGLITERAL(_, _) => Type::String,
+            Expr::BOOLLITERAL(_, _) => Type::Bool,
+            Expr::IDENTIFIER(_, span) => match self.resolution.references.get(span) {
+                Some(id) => self.typing.variable(*id).clone(),
+                None => Type::Unknown,
+            },
+            Expr::PARENTHESIZED(inner) => self.infer(inner),
+            Expr::UNARY(operand) => {
+                self.expect(Type::Bool, operand);
+                Type::Bool
+            }
+            Expr::BINARYEXPR(left, right, operator, operator_span) => {
+                let left_type = self.infer(left);
+                let right_type = self.infer(right);
+                match binary(operator, &left_type, &right_type) {
+                    Some(ty) => ty,
+                    None => {
+                        self.error(SemanticError::InvalidOperands {
+                            operator: operator.clone(),
+                            left: left_type,
+                            right: right_type,
+                            span: expr.span().unwrap_or(*operator_span),
+                        });
+                        Type::Unknown
+                    }
+                }
+            }
+            Expr::ARRAY(elements) => {
+                let mut element = Type::Unknown;
+                for item in elements {
+                    let found = self.infer(item);
+                    // the first element that isn't unknown decides the type of the array
+                    if !element.is_known() {
+                        element = found;
+                        continue;
+                    }
+                    match element.common(&found) {
+                        Some(common) if common.is_known() => element = common,
+                        Some(_) => {}
+                        None => self.error_at(item, |span| SemanticError::TypeMismatch {
+                            expected: element.clone(),
+                            found,
+                            span,
+                        }),
+                    }
+                }
+                Type::Array(Box::new(element))
+            }
+            Expr::INDEX(base, index) => {
+                let base_type = self.infer(base);
+                self.expect(Type::Int, index);
+                match base_type {
+                    Type::Array(element) => *element,
+                    Type::Unknown => Type::Unknown,
+                    found => {
+                        self.error_at(base, |span| SemanticError::NotIndexable { found, span });
+                        Type::Unknown
+                    }
+                }
+            }
+            Expr::ASSIGNMENT(target, value) => {
+                let target_type = self.infer(target);
+                self.expect(target_type.clone(), value);
+                target_type
+            }
+            Expr::MEMBER(object, field) => {
+                let object_type = self.infer(object);
+                if !object_type.is_known() {
+                    return Type::Unknown;
+                }
+                match self.field(&object_type, field) {
+                    Some(ty) => ty,
+                    None => {
+                        self.error_at(object, |span| SemanticError::NoField {
+                            ty: object_type,
+                            field: field.to_string(),
+                            span,
+                        });
+                        Type::Unknown
+                    }
+                }
+            }
+            Expr::CALL(callee, arguments) => self.call(expr, callee, arguments),
+            Expr::STRUCTLITERAL(name, fields) => {
+                let ty = self.named(name).unwrap_or(Type::Unknown);
+                if !self.structs.contains_key(name) {
+                    if let Some(span) = expr.span() {
+                        self.error(SemanticError::UnknownType { name: name.to_string(), span });
+                    }
+                }
+                for initializer in fields {
+                    match self.field(&ty, &initializer.name) {
+                        Some(expected) => self.expect(expected, &initializer.value),
+                        None => {
+                            if ty.is_known() {
+                                self.error(SemanticError::NoField {
+                                    ty: ty.clone(),
+                                    field: initializer.name.to_string(),
+                                    span: initializer.span,
+                                });
+                            }
+                            self.infer(&initializer.value);
+                        }
+                    }
+                }
+                ty
+            }
+            Expr::CONDITIONAL(condition, then, otherwise) => {
+                self.expect(Type::Bool, condition);
+                let then_type = self.infer(then);
+                let otherwise_type = self.infer(otherwise);
+                match then_type.common(&otherwise_type) {
+                    Some(ty) => ty,
+                    None => {
+                        self.error_at(otherwise, |span| SemanticError::TypeMismatch {
+                            expected: then_type,
+                            found: otherwise_type,
+                            span,
+                        });
+                        Type::Unknown
+                    }
+                }
+            }
+            Expr::PATH(_) | Expr::Error => Type::Unknown,
+        }
+    }
+}
+
+// the type of `left operator right`, `None` if the operator doesn't apply to those operands
+fn binary(operator: &TokenKind, left: &Type, right: &Type) -> Option<Type> {
+    match operator {
+        TokenKind::EQ | TokenKind::NEQ => left.common(right).map(|_| Type::Bool),
+        TokenKind::LT | TokenKind::GT | TokenKind::LTEQ | TokenKind::GTEQ => {
+            let ordered = |ty: &Type| ty.is_numeric() || !ty.is_known();
+            (ordered(left) && ordered(right)).then_some(Type::Bool)
+        }
+        _ => {
+            // `+` also joins strings
+            let operand = |ty: &Type| {
+                ty.is_numeric() || !ty.is_known() || (*operator == TokenKind::ADD && *ty == Type::String)
+            };
+            if !operand(left) || !operand(right) {
+                return None;
+            }
+            left.common(right)
+        }
+    }
+}
+
+impl Visitor for Checker<'_> {
+    fn visit_program(&mut self, program: &[Stmt]) {
+        self.hoist(program);
+        visit::walk_program(self, program);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VariableDeclaration(declaration) => self.variable_declaration(declaration),
+            Stmt::Block(statements) => {
+                self.hoist(statements);
+                visit::walk_stmt(self, stmt);
+            }
+            Stmt::IFSTATEMENT(condition, body) | Stmt::WHILE(condition, body) => {
+                self.expect(Type::Bool, condition);
+                self.visit_stmt(body);
+            }
+            Stmt::MATCH(scrutinee, arms) => {
+                let scrutinee_type = self.infer(scrutinee);
+                for arm in arms {
+                    match &arm.pattern {
+                        Pattern::Literal(literal, span) => {
+                            let found = self.infer(literal);
+                            if scrutinee_type.common(&found).is_none() {
+                                self.error(SemanticError::TypeMismatch {
+                                    expected: scrutinee_type.clone(),
+                                    found,
+                                    span: *span,
+                                });
+                            }
+                        }
+                        Pattern::Binding(_, span) => self.declare(*span, scrutinee_type.clone()),
+                        Pattern::Wildcard(_) => {}
+                    }
+                    self.visit_stmt(&arm.body);
+                }
+            }
+            Stmt::FunctionDeclaration(function) => self.function_declaration(function),
+            // outside of a function `return` ends the program, with any value
+            Stmt::RETURN(value, span) => match (self.returns.last().cloned(), value) {
+                (Some(expected), Some(value)) => self.expect(expected, value),
+                (Some(expected), None) => {
+                    if !expected.accepts(&Type::Void) {
+                        self.error(SemanticError::TypeMismatch { expected, found: Type::Void, span: *span });
+                    }
+                }
+                (None, Some(value)) => {
+                    self.infer(value);
+                }
+                (None, None) => {}
+            },
+            Stmt::EXPR(expr, _) => {
+                self.infer(expr);
+            }
+            _ => visit::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_field_declaration(&mut self, field: &FieldDeclaration) {
+        if self.named(&field.type_name).is_none() {
+            self.error(SemanticError::UnknownType { name: field.type_name.to_string(), span: field.span });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::check_flow;
+    use crate::fold::fold_constants;
+    use crate::tests::{reported, resolved, Resolved};
+
+    #[test]
+    fn test_type_checker_infers_and_reports_mismatches() {
+        let source = "struct Point { x: float, y: float }\nlet radius = 5;\nlet area = radius * radius * 3.14;\nlet big = area > 100;\nlet name: string = \"a\" * 3;\nif (5.0) { big = radius == 5.0; }\nlet p = Point { x: 1, z: true };\nlet count: int = area;\nlet label = big ? \"big\" : 0;\np.x[0];";
+        let Resolved { tree, resolution, .. } = resolved(source);
+        let typing = check_types(&tree.program, &resolution);
+
+        let types: Vec<(&str, &Type)> = resolution
+            .declarations
+            .iter()
+            .enumerate()
+            .map(|(index, declaration)| (declaration.name.as_str(), typing.variable(DeclarationId(index))))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("radius", &Type::Int),
+                ("area", &Type::Float),
+                ("big", &Type::Bool),
+                ("name", &Type::String),
+                ("p", &Type::Struct("Point".to_string())),
+                ("count", &Type::Int),
+                ("label", &Type::Unknown),
+            ]
+        );
+
+        assert_eq!(
+            reported(&typing.errors),
+            [
+                "5: E0302 operator '*' can't be applied to string and int",
+                "6: E0301 mismatched types, expected bool found float",
+                "7: E0305 type Point has no field 'z'",
+                "8: E0303 'count' is declared as int but its initializer is float",
+                "9: E0301 mismatched types, expected string found int",
+                "10: E0306 float can't be indexed, only arrays can",
+            ]
+        );
+        assert!(matches!(&typing.errors[0], SemanticError::InvalidOperands { span, .. } if (span.start_column, span.end_column) == (20, 26)));
+    }
+
+    #[test]
+    fn test_calls_are_checked_against_the_function_signature() {
+        let source = "function add(a: int, b: float): float {\n    return a + b;\n}\nfunction sign(x: int): int {\n    if (x < 0) {\n        return 0 - 1;\n    }\n}\nfunction log(message: string) {\n    return;\n}\nlet total = add(1, 2.5);\nadd(1, \"two\");\nadd(1, 2, 3, 4);\nadd(1);\nlet count = 3;\ncount(1);\nlog(\"done\");\nfunction fact(n: int): int {\n    while (true) {\n        return n * fact(n - 1);\n    }\n}";
+        let Resolved { tree, ast, resolution } = resolved(source);
+        assert_eq!(crate::printer::print_program(&tree.program).matches("function ").count(), 4);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+        let typing = check_types(&tree.program, &resolution);
+
+        assert_eq!(
+            reported(&typing.errors),
+            [
+                "13: E0308 mismatched argument to 'add', expected float found string",
+                "14: E0307 'add' takes 2 arguments but 4 were given",
+                "15: E0307 'add' takes 2 arguments but 1 was given",
+                "17: E0309 int can't be called, only functions can",
+            ]
+        );
+        // the extra arguments are covered, and every call error points back at the signature
+        let columns: Vec<u32> = typing.errors.iter().map(|error| error.span().start_column).collect();
+        assert_eq!(columns, [8, 11, 1, 1]);
+        assert!(matches!(typing.errors[1], SemanticError::ArgumentCount { span, .. } if span.end_column == 14));
+        let related = typing.errors[0].related();
+        assert_eq!(related[0].1, "'add' is declared here");
+        assert_eq!((related[0].0.start_line, related[0].0.start_column, related[0].0.end_column), (1, 1, 37));
+        let total = resolution.declarations.iter().position(|declaration| declaration.name == "total").unwrap();
+        assert_eq!(typing.variable(DeclarationId(total)), &Type::Float);
+
+        let errors = check_flow(&ast, &fold_constants(&ast, &resolution));
+        assert_eq!(reported(&errors), ["4: E0502 'sign' returns int but can end without returning a value"]);
+    }
+}
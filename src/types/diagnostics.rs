@@ -1,6 +1,9 @@
-use super::Range;
+use super::{Location, Range};
 use serde::Serialize;
 
+use crate::error::{ParseError, SemanticError};
+use crate::loader::ImportError;
+
 #[derive(Serialize)]
 pub struct PublishDiagnosticsParams {
     pub uri: String,
@@ -8,11 +11,86 @@ pub struct PublishDiagnosticsParams {
     pub diagnostics: Vec<Diagnostic>
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct Diagnostic {
     pub range: Range,
     pub message: String,
-    pub severity: u32
+    pub severity: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<u32>,
+    #[serde(rename = "relatedInformation", skip_serializing_if = "Vec::is_empty")]
+    pub related_information: Vec<DiagnosticRelatedInformation>
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DiagnosticRelatedInformation {
+    pub location: Location,
+    pub message: String
+}
+
+impl Diagnostic {
+    // errors only know positions, the document their related locations are in is set here
+    pub fn in_document(mut self, uri: &str) -> Diagnostic {
+        for related in &mut self.related_information {
+            related.location.uri = uri.to_string();
+        }
+        self
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Diagnostic {
+        Diagnostic {
+            range: error.span().range(),
+            message: error.to_string(),
+            severity: error.severity() as u32,
+            code: Some(error.code().to_string()),
+            tags: Vec::new(),
+            related_information: error
+                .related()
+                .into_iter()
+                .map(|(span, message)| DiagnosticRelatedInformation {
+                    location: Location { uri: String::new(), range: span.range() },
+                    message
+                })
+                .collect()
+        }
+    }
+}
+
+impl From<&ImportError> for Diagnostic {
+    fn from(error: &ImportError) -> Diagnostic {
+        Diagnostic {
+            range: error.span().map(|span| span.range()).unwrap_or_default(),
+            message: error.to_string(),
+            severity: 1,
+            code: Some(error.code().to_string()),
+            tags: Vec::new(),
+            related_information: Vec::new()
+        }
+    }
+}
+
+impl From<&SemanticError> for Diagnostic {
+    fn from(error: &SemanticError) -> Diagnostic {
+        Diagnostic {
+            range: error.span().range(),
+            message: error.to_string(),
+            severity: error.severity() as u32,
+            code: Some(error.code().to_string()),
+            tags: error.tags().into_iter().map(|tag| tag as u32).collect(),
+            related_information: error
+                .related()
+                .into_iter()
+                .map(|(span, message)| DiagnosticRelatedInformation {
+                    location: Location { uri: String::new(), range: span.range() },
+                    message
+                })
+                .collect()
+        }
+    }
 }
 
 
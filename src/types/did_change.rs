@@ -1,6 +1,8 @@
 use serde::{Serialize, Deserialize};
 use serde_json;
 
+use super::Range;
+
 
 #[derive(Deserialize, Serialize)]
 pub struct DidChangeNotification {
@@ -17,8 +19,10 @@ pub struct ChangeParams {
 }
 
 
+// with incremental sync `range` is the replaced text, without it `text` is the whole document
 #[derive(Deserialize, Serialize)]
 pub struct ContentChangeEvent {
+   pub range: Option<Range>,
    pub text: String 
 }
 
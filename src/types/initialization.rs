@@ -8,6 +8,26 @@ struct InitializationRequest {
 }
 
 
+#[derive(Deserialize, Default)]
+pub struct InitializeRequest {
+    #[serde(default)]
+    pub params: InitializeParams,
+}
+
+#[derive(Deserialize, Default)]
+pub struct InitializeParams {
+    #[serde(default)]
+    pub initializationOptions: Option<InitializationOptions>,
+}
+
+// settings the client passes when starting the server, every one of them is off unless given
+#[derive(Deserialize, Default, Clone, Copy)]
+pub struct InitializationOptions {
+    #[serde(default, rename = "warnShadowing")]
+    pub warn_shadowing: bool,
+}
+
+
 #[derive(Deserialize, Serialize)]
 pub struct InitializationResponse {
     pub jsonrpc: String,
@@ -15,14 +15,21 @@ pub struct Request {
 }
 
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
 pub struct Range {
     pub start: Position,
     pub end: Position
 }
 
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Location {
+    pub uri: String,
+    pub range: Range
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
 pub struct Position{
     pub line: u32,
     pub character: u32
@@ -0,0 +1,337 @@
+// traversal of the `Stmt`/`Expr` AST.
+//
+// a pass implements `Visitor` (or `VisitorMut` to rewrite the tree in place) and
+// overrides the methods for the nodes it cares about. the default methods call
+// the matching `walk_*` function, which visits the children in source order, so
+// an override calls `walk_*` itself to keep descending or leaves it out to skip
+// the subtree.
+
+use crate::parser::{Expr, FieldDeclaration, FieldInitializer, MatchArm, Pattern, Stmt};
+use crate::tokenizer::Span;
+
+pub trait Visitor {
+    fn visit_program(&mut self, program: &[Stmt]) {
+        walk_program(self, program);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_field_declaration(&mut self, field: &FieldDeclaration) {
+        walk_field_declaration(self, field);
+    }
+
+    fn visit_field_initializer(&mut self, field: &FieldInitializer) {
+        walk_field_initializer(self, field);
+    }
+
+    fn visit_match_arm(&mut self, arm: &MatchArm) {
+        walk_match_arm(self, arm);
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        walk_pattern(self, pattern);
+    }
+
+    fn visit_span(&mut self, _span: &Span) {}
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &[Stmt]) {
+    for stmt in program {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match stmt {
+        Stmt::EOF => {}
+        Stmt::VariableDeclaration(declaration) => {
+            visitor.visit_span(&declaration.span);
+            if let Some(annotation) = &declaration.annotation {
+                visitor.visit_span(&annotation.span);
+            }
+            if let Some(initializer) = &declaration.initializer {
+                visitor.visit_expr(initializer);
+            }
+        }
+        Stmt::StructDeclaration(_, fields) => {
+            for field in fields {
+                visitor.visit_field_declaration(field);
+            }
+        }
+        Stmt::FunctionDeclaration(function) => {
+            visitor.visit_span(&function.span);
+            visitor.visit_span(&function.signature);
+            for parameter in &function.parameters {
+                visitor.visit_span(&parameter.span);
+                visitor.visit_span(&parameter.annotation.span);
+            }
+            if let Some(return_type) = &function.return_type {
+                visitor.visit_span(&return_type.span);
+            }
+            visitor.visit_stmt(&function.body);
+        }
+        Stmt::IFSTATEMENT(condition, body) | Stmt::WHILE(condition, body) => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(body);
+        }
+        Stmt::RETURN(value, span) => {
+            visitor.visit_span(span);
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        Stmt::BREAK(span) | Stmt::CONTINUE(span) => visitor.visit_span(span),
+        Stmt::Block(statements) => {
+            for statement in statements {
+                visitor.visit_stmt(statement);
+            }
+        }
+        Stmt::MATCH(scrutinee, arms) => {
+            visitor.visit_expr(scrutinee);
+            for arm in arms {
+                visitor.visit_match_arm(arm);
+            }
+        }
+        Stmt::IMPORT(_, span) => visitor.visit_span(span),
+        Stmt::EXPR(expr, span) => {
+            visitor.visit_expr(expr);
+            visitor.visit_span(span);
+        }
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::PATH(_) | Expr::Error => {}
+        Expr::INTEGERLITERAL(_, span)
+        | Expr::FLOATLITERAL(_, span)
+        | Expr::STRINGLITERAL(_, span)
+        | Expr::BOOLLITERAL(_, span)
+        | Expr::IDENTIFIER(_, span) => visitor.visit_span(span),
+        Expr::BINARYEXPR(left, right, _, operator) => {
+            visitor.visit_expr(left);
+            visitor.visit_span(operator);
+            visitor.visit_expr(right);
+        }
+        Expr::PARENTHESIZED(inner) | Expr::UNARY(inner) | Expr::MEMBER(inner, _) => visitor.visit_expr(inner),
+        Expr::ARRAY(elements) => {
+            for element in elements {
+                visitor.visit_expr(element);
+            }
+        }
+        Expr::INDEX(left, right) | Expr::ASSIGNMENT(left, right) => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        Expr::CALL(callee, arguments) => {
+            visitor.visit_expr(callee);
+            for argument in arguments {
+                visitor.visit_expr(argument);
+            }
+        }
+        Expr::STRUCTLITERAL(_, fields) => {
+            for field in fields {
+                visitor.visit_field_initializer(field);
+            }
+        }
+        Expr::CONDITIONAL(condition, then, otherwise) => {
+            visitor.visit_expr(condition);
+            visitor.visit_expr(then);
+            visitor.visit_expr(otherwise);
+        }
+    }
+}
+
+pub fn walk_field_declaration<V: Visitor + ?Sized>(visitor: &mut V, field: &FieldDeclaration) {
+    visitor.visit_span(&field.span);
+}
+
+pub fn walk_field_initializer<V: Visitor + ?Sized>(visitor: &mut V, field: &FieldInitializer) {
+    visitor.visit_span(&field.span);
+    visitor.visit_expr(&field.value);
+}
+
+pub fn walk_match_arm<V: Visitor + ?Sized>(visitor: &mut V, arm: &MatchArm) {
+    visitor.visit_pattern(&arm.pattern);
+    visitor.visit_stmt(&arm.body);
+}
+
+pub fn walk_pattern<V: Visitor + ?Sized>(visitor: &mut V, pattern: &Pattern) {
+    match pattern {
+        Pattern::Literal(literal, span) => {
+            visitor.visit_expr(literal);
+            visitor.visit_span(span);
+        }
+        Pattern::Wildcard(span) | Pattern::Binding(_, span) => visitor.visit_span(span),
+    }
+}
+
+// same as `Visitor` but every node is handed out mutably
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut [Stmt]) {
+        walk_program_mut(self, program);
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+
+    fn visit_field_declaration_mut(&mut self, field: &mut FieldDeclaration) {
+        walk_field_declaration_mut(self, field);
+    }
+
+    fn visit_field_initializer_mut(&mut self, field: &mut FieldInitializer) {
+        walk_field_initializer_mut(self, field);
+    }
+
+    fn visit_match_arm_mut(&mut self, arm: &mut MatchArm) {
+        walk_match_arm_mut(self, arm);
+    }
+
+    fn visit_pattern_mut(&mut self, pattern: &mut Pattern) {
+        walk_pattern_mut(self, pattern);
+    }
+
+    fn visit_span_mut(&mut self, _span: &mut Span) {}
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut [Stmt]) {
+    for stmt in program {
+        visitor.visit_stmt_mut(stmt);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Stmt) {
+    match stmt {
+        Stmt::EOF => {}
+        Stmt::VariableDeclaration(declaration) => {
+            visitor.visit_span_mut(&mut declaration.span);
+            if let Some(annotation) = &mut declaration.annotation {
+                visitor.visit_span_mut(&mut annotation.span);
+            }
+            if let Some(initializer) = &mut declaration.initializer {
+                visitor.visit_expr_mut(initializer);
+            }
+        }
+        Stmt::StructDeclaration(_, fields) => {
+            for field in fields {
+                visitor.visit_field_declaration_mut(field);
+            }
+        }
+        Stmt::FunctionDeclaration(function) => {
+            visitor.visit_span_mut(&mut function.span);
+            visitor.visit_span_mut(&mut function.signature);
+            for parameter in &mut function.parameters {
+                visitor.visit_span_mut(&mut parameter.span);
+                visitor.visit_span_mut(&mut parameter.annotation.span);
+            }
+            if let Some(return_type) = &mut function.return_type {
+                visitor.visit_span_mut(&mut return_type.span);
+            }
+            visitor.visit_stmt_mut(&mut function.body);
+        }
+        Stmt::IFSTATEMENT(condition, body) | Stmt::WHILE(condition, body) => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stmt_mut(body);
+        }
+        Stmt::RETURN(value, span) => {
+            visitor.visit_span_mut(span);
+            if let Some(value) = value {
+                visitor.visit_expr_mut(value);
+            }
+        }
+        Stmt::BREAK(span) | Stmt::CONTINUE(span) => visitor.visit_span_mut(span),
+        Stmt::Block(statements) => {
+            for statement in statements {
+                visitor.visit_stmt_mut(statement);
+            }
+        }
+        Stmt::MATCH(scrutinee, arms) => {
+            visitor.visit_expr_mut(scrutinee);
+            for arm in arms {
+                visitor.visit_match_arm_mut(arm);
+            }
+        }
+        Stmt::IMPORT(_, span) => visitor.visit_span_mut(span),
+        Stmt::EXPR(expr, span) => {
+            visitor.visit_expr_mut(expr);
+            visitor.visit_span_mut(span);
+        }
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::PATH(_) | Expr::Error => {}
+        Expr::INTEGERLITERAL(_, span)
+        | Expr::FLOATLITERAL(_, span)
+        | Expr::STRINGLITERAL(_, span)
+        | Expr::BOOLLITERAL(_, span)
+        | Expr::IDENTIFIER(_, span) => visitor.visit_span_mut(span),
+        Expr::BINARYEXPR(left, right, _, operator) => {
+            visitor.visit_expr_mut(left);
+            visitor.visit_span_mut(operator);
+            visitor.visit_expr_mut(right);
+        }
+        Expr::PARENTHESIZED(inner) | Expr::UNARY(inner) | Expr::MEMBER(inner, _) => visitor.visit_expr_mut(inner),
+        Expr::ARRAY(elements) => {
+            for element in elements {
+                visitor.visit_expr_mut(element);
+            }
+        }
+        Expr::INDEX(left, right) | Expr::ASSIGNMENT(left, right) => {
+            visitor.visit_expr_mut(left);
+            visitor.visit_expr_mut(right);
+        }
+        Expr::CALL(callee, arguments) => {
+            visitor.visit_expr_mut(callee);
+            for argument in arguments {
+                visitor.visit_expr_mut(argument);
+            }
+        }
+        Expr::STRUCTLITERAL(_, fields) => {
+            for field in fields {
+                visitor.visit_field_initializer_mut(field);
+            }
+        }
+        Expr::CONDITIONAL(condition, then, otherwise) => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_expr_mut(then);
+            visitor.visit_expr_mut(otherwise);
+        }
+    }
+}
+
+pub fn walk_field_declaration_mut<V: VisitorMut + ?Sized>(visitor: &mut V, field: &mut FieldDeclaration) {
+    visitor.visit_span_mut(&mut field.span);
+}
+
+pub fn walk_field_initializer_mut<V: VisitorMut + ?Sized>(visitor: &mut V, field: &mut FieldInitializer) {
+    visitor.visit_span_mut(&mut field.span);
+    visitor.visit_expr_mut(&mut field.value);
+}
+
+pub fn walk_match_arm_mut<V: VisitorMut + ?Sized>(visitor: &mut V, arm: &mut MatchArm) {
+    visitor.visit_pattern_mut(&mut arm.pattern);
+    visitor.visit_stmt_mut(&mut arm.body);
+}
+
+pub fn walk_pattern_mut<V: VisitorMut + ?Sized>(visitor: &mut V, pattern: &mut Pattern) {
+    match pattern {
+        Pattern::Literal(literal, span) => {
+            visitor.visit_expr_mut(literal);
+            visitor.visit_span_mut(span);
+        }
+        Pattern::Wildcard(span) | Pattern::Binding(_, span) => visitor.visit_span_mut(span),
+    }
+}